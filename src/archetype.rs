@@ -3,7 +3,9 @@ use std::collections::HashMap;
 use collections::{BitSet, SparseMap};
 
 use crate::{
-    component::{storage::ComponentStorage, Component, ComponentID, ComponentManager},
+    component::{
+        bundle::Bundle, storage::ComponentStorage, Component, ComponentID, ComponentManager,
+    },
     entity::{Entity, EntityManager},
     util::get_two_mut_unchecked,
     ComponentBundle,
@@ -52,6 +54,23 @@ impl Archetype {
         self.component_id_bitset.test(comp_id)
     }
 
+    /// Reserves capacity for at least `additional` more entities, in the entities vec as well as in
+    /// every component storage.
+    pub fn reserve(&mut self, additional: usize) {
+        self.entities.reserve(additional);
+
+        for storage in self.components.values_mut() {
+            storage.reserve(additional);
+        }
+    }
+
+    /// Clamps every tick that is becoming too old to be compared with the current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        for storage in self.components.values_mut() {
+            storage.check_ticks(this_run);
+        }
+    }
+
     /// # Safety
     /// - The entity must be alive, and does not already exist in this archetype.
     pub unsafe fn push_entity(&mut self, entity: Entity, entity_manager: &mut EntityManager) {
@@ -83,10 +102,55 @@ impl Archetype {
     /// - The concrete type associated with the component must match the type of an underlying
     ///   component storage within this archetype.
     /// - The component ID must exist within this archetype, as no bounds checking is performed.
-    pub unsafe fn push_component<C: Component>(&mut self, comp_id: ComponentID, component: C) {
+    pub unsafe fn push_component<C: Component>(
+        &mut self,
+        comp_id: ComponentID,
+        component: C,
+        tick: u32,
+    ) {
         // SAFETY: Deferred to the caller
         let storage = unsafe { self.get_mut_storage(comp_id) };
-        unsafe { storage.push(component) };
+        unsafe { storage.push(component, tick) };
+    }
+
+    /// # Safety
+    /// - The concrete type associated with the component must match the type of an underlying
+    ///   component storage within this archetype.
+    /// - The component ID must exist within this archetype, as no bounds checking is performed.
+    /// - The row must be within the bounds of the underlying vec.
+    pub unsafe fn replace_component<C: Component>(
+        &mut self,
+        comp_id: ComponentID,
+        row: usize,
+        component: C,
+        tick: u32,
+    ) {
+        // SAFETY: Deferred to the caller
+        let storage = unsafe { self.get_mut_storage(comp_id) };
+        unsafe { storage.replace(row, component, tick) };
+    }
+
+    /// Overwrites the component at the given row if the storage has it, otherwise pushes it onto the end
+    ///
+    /// # Safety
+    /// - The concrete type associated with the component must match the type of an underlying
+    ///   component storage within this archetype.
+    /// - The component ID must exist within this archetype, as no bounds checking is performed.
+    /// - The row must be within the bounds of the underlying vec, or equal to its length.
+    pub unsafe fn insert_component<C: Component>(
+        &mut self,
+        comp_id: ComponentID,
+        row: usize,
+        component: C,
+        tick: u32,
+    ) {
+        // SAFETY: Deferred to the caller
+        let storage = unsafe { self.get_mut_storage(comp_id) };
+        if row < storage.len() {
+            unsafe { storage.replace(row, component, tick) };
+        } else {
+            unsafe { storage.push(component, tick) };
+        }
     }
 
     /// # Safety
@@ -129,12 +193,13 @@ impl Archetype {
         comp_id: ComponentID,
         src_row: usize,
         dst_arche: &mut Self,
+        tick: u32,
     ) {
         // SAFETY: Deferred to the caller
         let src_storage = unsafe { self.get_mut_storage(comp_id) };
         let dst_storage = unsafe { dst_arche.get_mut_storage(comp_id) };
 
-        unsafe { src_storage.transfer(src_row, dst_storage) }
+        unsafe { src_storage.transfer(src_row, dst_storage, tick) }
     }
 
     /// # Safety
@@ -147,6 +212,7 @@ impl Archetype {
         comp_ids: impl Iterator<Item = ComponentID>,
         dst_arche: &mut Archetype,
         entity_manager: &mut EntityManager,
+        tick: u32,
     ) {
         // SAFETY: Caller ensures that the entity is alive.
         let entity_record = unsafe { entity_manager.get_record(entity) };
@@ -155,7 +221,9 @@ impl Archetype {
             // SAFETY:
             // - Caller ensures component ID is valid for both archetypes.
             // - Entity is alive, so archetype_row is assumed to be valid
-            unsafe { self.transfer_component(comp_id, entity_record.archetype_row, dst_arche) };
+            unsafe {
+                self.transfer_component(comp_id, entity_record.archetype_row, dst_arche, tick)
+            };
         }
 
         // SAFETY: Entity is alive and exists within this archetype
@@ -199,24 +267,19 @@ pub struct ArchetypeManager {
 
     /// A table of all archetypes that exist within the world.
     pub(crate) archetype_table: Vec<Archetype>,
-
-    /// When a query is first created, archetypes relevant to that query are cached. If a new archetype
-    /// is created it is added to this queue so that, after all systems have run for a given world update,
-    /// they can be checked for relevance to every query and added to their cache, before being cleared.
-    pub(crate) new_archetypes_queue: Vec<ArchetypeID>,
 }
 
 impl ArchetypeManager {
     pub fn new() -> Self {
-        let ids = HashMap::with_capacity_and_hasher(8, ahash::RandomState::default());
+        let mut ids = HashMap::with_capacity_and_hasher(8, ahash::RandomState::default());
 
         // Includes root archetype
         let archetype_table = vec![Archetype::new(0, BitSet::new())];
+        ids.insert(BitSet::new(), 0);
 
         Self {
             ids,
             archetype_table,
-            new_archetypes_queue: Vec::new(),
         }
     }
 
@@ -233,7 +296,6 @@ impl ArchetypeManager {
         let arche = Archetype::new(arche_id, comp_ids.clone());
         self.archetype_table.push(arche);
         self.ids.insert(comp_ids, arche_id);
-        self.new_archetypes_queue.push(arche_id);
 
         arche_id
     }
@@ -262,6 +324,23 @@ impl ArchetypeManager {
         unsafe { self.archetype_table.get_unchecked_mut(arche_id) }
     }
 
+    /// Clamps every tick that is becoming too old to be compared with the current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        for archetype in self.archetype_table.iter_mut() {
+            archetype.check_ticks(this_run);
+        }
+    }
+
+    /// Enables tracking for the storages of the component in every archetype that has it
+    pub fn enable_tracking(&mut self, comp_id: ComponentID) {
+        for archetype in self.archetype_table.iter_mut() {
+            if archetype.has_component(comp_id) {
+                // SAFETY: The archetype has the component
+                unsafe { archetype.get_mut_storage(comp_id).enable_tracking() };
+            }
+        }
+    }
+
     /// # Safety
     /// - The entity must be alive.
     pub unsafe fn delete_entity(&mut self, entity: Entity, entity_manager: &mut EntityManager) {
@@ -309,6 +388,7 @@ impl ArchetypeManager {
         entity: Entity,
         comp_manager: &ComponentManager,
         entity_manager: &mut EntityManager,
+        tick: u32,
     ) {
         let comp_id = comp_manager.get_id::<T>();
 
@@ -328,7 +408,7 @@ impl ArchetypeManager {
 
         // SAFETY: The destination archetype is guaranteed to have the component ID as it has
         //         been extended to include the component ID.
-        unsafe { dst_arche.push_component(comp_id, component) };
+        unsafe { dst_arche.push_component(comp_id, component, tick) };
 
         // HACK: Get around borrow checker by redefining slice with different lifetime, until I find a
         //       better way to do this. These component IDs are read from a different part of the archetype
@@ -351,7 +431,7 @@ impl ArchetypeManager {
         //   component IDs from the source archetype.
         // - As we are adding a component, in moving to the destination archetype, the destination
         //   archetype will have the component IDs of the source archetype.
-        unsafe { src_arche.transfer_entity(entity, comp_ids, dst_arche, entity_manager) };
+        unsafe { src_arche.transfer_entity(entity, comp_ids, dst_arche, entity_manager, tick) };
     }
 
     /// # Safety
@@ -364,6 +444,7 @@ impl ArchetypeManager {
         entity: Entity,
         comp_manager: &ComponentManager,
         entity_manager: &mut EntityManager,
+        tick: u32,
     ) {
         let comp_id = comp_manager.get_id::<T>();
 
@@ -406,7 +487,198 @@ impl ArchetypeManager {
         //   component IDs from the destination archetype.
         // - As we are removing a component, in moving to the destination archetype, the source
         //   archetype will have the component IDs of the destination archetype.
-        unsafe { src_arche.transfer_entity(entity, comp_ids, dst_arche, entity_manager) };
+        unsafe { src_arche.transfer_entity(entity, comp_ids, dst_arche, entity_manager, tick) };
+    }
+
+    /// Creates an entity with every component in the bundle. The entity is only created once the bundle has
+    /// been validated, so a panic leaves no entity behind.
+    ///
+    /// # Panics
+    /// - If any component in the bundle has not been registered with the component manager.
+    /// - If the same component appears more than once in the bundle.
+    pub fn spawn_bundle<B: Bundle>(
+        &mut self,
+        bundle: B,
+        comp_manager: &ComponentManager,
+        entity_manager: &mut EntityManager,
+        tick: u32,
+    ) -> Entity {
+        let bundle_id = B::parameter_ids(comp_manager);
+
+        // SAFETY: The root archetype is always present
+        let dst_arche_id =
+            unsafe { self.get_extended_archetype_by(0, bundle_id.as_ref(), comp_manager) };
+
+        // SAFETY: `dst_arche_id` was just retrieved from this manager
+        let dst_arche = unsafe { self.get_mut(dst_arche_id) };
+
+        let entity = entity_manager.create();
+
+        // SAFETY: The destination archetype has been extended to include every component in the bundle
+        unsafe { bundle.push_components(dst_arche, &bundle_id, tick) };
+        // SAFETY: The entity was just created, so is alive and does not exist in any archetype
+        unsafe { dst_arche.push_entity(entity, entity_manager) };
+
+        entity
+    }
+
+    /// Creates an entity for each bundle, placing them all into the same archetype. The archetype is only
+    /// resolved once, and space is reserved up front based on the lower bound of the iterator's size hint.
+    ///
+    /// # Panics
+    /// - If any component in the bundle has not been registered with the component manager.
+    /// - If the same component appears more than once in the bundle. This is checked before any entity is
+    ///   created.
+    pub fn spawn_batch<B: Bundle>(
+        &mut self,
+        bundles: impl IntoIterator<Item = B>,
+        comp_manager: &ComponentManager,
+        entity_manager: &mut EntityManager,
+        tick: u32,
+    ) -> Vec<Entity> {
+        let bundles = bundles.into_iter();
+        let bundle_id = B::parameter_ids(comp_manager);
+
+        // SAFETY: The root archetype is always present
+        let dst_arche_id =
+            unsafe { self.get_extended_archetype_by(0, bundle_id.as_ref(), comp_manager) };
+
+        // SAFETY: `dst_arche_id` was just retrieved from this manager
+        let dst_arche = unsafe { self.get_mut(dst_arche_id) };
+
+        let (additional, _) = bundles.size_hint();
+        dst_arche.reserve(additional);
+        entity_manager.reserve(additional);
+
+        let mut entities = Vec::with_capacity(additional);
+        for bundle in bundles {
+            let entity = entity_manager.create();
+
+            // SAFETY: The destination archetype has been extended to include every component in the bundle
+            unsafe { bundle.push_components(dst_arche, &bundle_id, tick) };
+            // SAFETY: The entity was just created, so is alive and does not exist in any archetype
+            unsafe { dst_arche.push_entity(entity, entity_manager) };
+
+            entities.push(entity);
+        }
+
+        entities
+    }
+
+    /// Inserts every component in the bundle for the entity, moving it to its new archetype at most once.
+    /// Components that the entity already has are overwritten, keeping the tick that they were added at.
+    ///
+    /// # Safety
+    /// - The entity must be alive.
+    ///
+    /// # Panics
+    /// - If any component in the bundle has not been registered with the component manager.
+    pub unsafe fn insert_bundle<B: Bundle>(
+        &mut self,
+        bundle: B,
+        entity: Entity,
+        comp_manager: &ComponentManager,
+        entity_manager: &mut EntityManager,
+        tick: u32,
+    ) {
+        let bundle_id = B::parameter_ids(comp_manager);
+        let comp_ids = bundle_id.as_ref();
+
+        // SAFETY: Caller ensures that the entity is alive
+        let entity_record = unsafe { entity_manager.get_record(entity) };
+
+        let src_arche_id = entity_record.archetype_id;
+        let row = entity_record.archetype_row;
+
+        // SAFETY: `src_arche_id`, as retrieved from the entity record, is guaranteed to be valid
+        //        as it was copied from the archetype itself, and we do not delete archetypes.
+        let dst_arche_id =
+            unsafe { self.get_extended_archetype_by(src_arche_id, comp_ids, comp_manager) };
+
+        if src_arche_id == dst_arche_id {
+            // The entity already has every component in the bundle, so there is nothing to move
+            let arche = unsafe { self.get_mut(src_arche_id) };
+            // SAFETY: The archetype contains every component, and the row of an alive entity is valid
+            unsafe { bundle.replace_components(arche, &bundle_id, row, tick) };
+            return;
+        }
+
+        // SAFETY: Archetypes are guaranteed to exist and be unique, so we can safely get mutable references
+        let (src_arche, dst_arche) =
+            unsafe { get_two_mut_unchecked(&mut self.archetype_table, src_arche_id, dst_arche_id) };
+
+        // Components that are in the bundle are moved too, so that they are overwritten in the destination
+        // and keep the tick that they were added at
+        for storage in src_arche.components.values_mut() {
+            // SAFETY: The destination archetype has been extended from the source archetype, so contains
+            //         all of its components
+            unsafe { storage.transfer(row, dst_arche.get_mut_storage(storage.id()), tick) };
+        }
+
+        // SAFETY:
+        // - The destination archetype has been extended to include every component in the bundle
+        // - The entity will be pushed to the end of the destination archetype. Its transferred
+        //   components are already in that row, while the others have yet to be pushed
+        let dst_row = dst_arche.entities.len();
+        unsafe { bundle.insert_components(dst_arche, &bundle_id, dst_row, tick) };
+
+        // SAFETY: Entity is alive and exists within the source archetype only
+        unsafe { src_arche.delete_entity(entity, entity_manager) };
+        unsafe { dst_arche.push_entity(entity, entity_manager) };
+    }
+
+    /// Removes every component in the bundle that the entity has, moving it to its new archetype at most
+    /// once. The removed components are dropped.
+    ///
+    /// # Safety
+    /// - The entity must be alive.
+    ///
+    /// # Panics
+    /// - If any component in the bundle has not been registered with the component manager.
+    pub unsafe fn remove_bundle<B: Bundle>(
+        &mut self,
+        entity: Entity,
+        comp_manager: &ComponentManager,
+        entity_manager: &mut EntityManager,
+        tick: u32,
+    ) {
+        let bundle_id = B::parameter_ids(comp_manager);
+
+        // SAFETY: Caller ensures that the entity is alive
+        let entity_record = unsafe { entity_manager.get_record(entity) };
+
+        let src_arche_id = entity_record.archetype_id;
+        let row = entity_record.archetype_row;
+
+        // SAFETY: `src_arche_id`, as retrieved from the entity record, is guaranteed to be valid
+        //        as it was copied from the archetype itself, and we do not delete archetypes.
+        let dst_arche_id =
+            unsafe { self.get_reduced_archetype_by(src_arche_id, bundle_id.as_ref()) };
+
+        if src_arche_id == dst_arche_id {
+            // The entity has none of the components in the bundle
+            return;
+        }
+
+        // SAFETY: Archetypes are guaranteed to exist and be unique, so we can safely get mutable references
+        let (src_arche, dst_arche) =
+            unsafe { get_two_mut_unchecked(&mut self.archetype_table, src_arche_id, dst_arche_id) };
+
+        for storage in src_arche.components.values_mut() {
+            let comp_id = storage.id();
+
+            if dst_arche.has_component(comp_id) {
+                // SAFETY: Entity is alive, so the row is valid, and the destination archetype has the component
+                unsafe { storage.transfer(row, dst_arche.get_mut_storage(comp_id), tick) };
+            } else {
+                // SAFETY: Entity is alive, so the row is valid
+                unsafe { storage.delete(row) };
+            }
+        }
+
+        // SAFETY: Entity is alive and exists within the source archetype only
+        unsafe { src_arche.delete_entity(entity, entity_manager) };
+        unsafe { dst_arche.push_entity(entity, entity_manager) };
     }
 
     /// # Safety
@@ -510,11 +782,248 @@ impl ArchetypeManager {
 
         unsafe { self.insert_graph_edge(src_arche_id, dst_arche_id, old_comp_id) };
 
+        dst_arche_id
+    }
+    /// Similar to [ArchetypeManager::get_extended_archetype], but extends the source archetype with
+    /// several components at once. Component IDs that the source archetype already has are ignored,
+    /// so the source archetype itself is returned if it has all of them.
+    ///
+    /// Graph edges are not recorded, as they only represent the addition of a single component.
+    ///
+    /// # Safety
+    /// - `src_arche_id` must be a valid archetype within this manager.
+    pub unsafe fn get_extended_archetype_by(
+        &mut self,
+        src_arche_id: ArchetypeID,
+        new_comp_ids: &[ComponentID],
+        comp_manager: &ComponentManager,
+    ) -> ArchetypeID {
+        let src_arche = unsafe { self.get(src_arche_id) };
+
+        let target_comp_bitset = {
+            let mut bitset = src_arche.component_id_bitset.clone();
+            for &comp_id in new_comp_ids {
+                bitset.set(comp_id);
+            }
+            bitset
+        };
+
+        if let Some(&dst_arche_id) = self.ids.get(&target_comp_bitset) {
+            return dst_arche_id;
+        }
+
+        // Archetype with the components did not exist, so create it
+        let dst_arche_id = self.create_archetype(target_comp_bitset);
+        // SAFETY: Archetypes are guaranteed to exist and be unique, so we can safely get mutable references
+        let (src_arche, dst_arche) =
+            unsafe { get_two_mut_unchecked(&mut self.archetype_table, src_arche_id, dst_arche_id) };
+
+        // add the new component storages to the archetype
+        for &comp_id in new_comp_ids {
+            if src_arche.has_component(comp_id) {
+                continue;
+            }
+
+            dst_arche.components.insert(
+                comp_id,
+                ComponentStorage::from_metadata(comp_id, comp_manager.get_metadata(comp_id)),
+            );
+        }
+
+        // add the other components storages, inherited from the src archetype
+        for comp_storage in src_arche.components.values() {
+            dst_arche.components.insert(
+                comp_storage.id(),
+                ComponentStorage::from_other(comp_storage),
+            );
+        }
+
+        dst_arche_id
+    }
+
+    /// Similar to [ArchetypeManager::get_reduced_archetype], but removes several components from the
+    /// source archetype at once. Component IDs that the source archetype does not have are ignored,
+    /// so the source archetype itself is returned if it has none of them.
+    ///
+    /// Graph edges are not recorded, as they only represent the removal of a single component.
+    ///
+    /// # Safety
+    /// - `src_arche_id` must be a valid archetype within this manager.
+    pub unsafe fn get_reduced_archetype_by(
+        &mut self,
+        src_arche_id: ArchetypeID,
+        old_comp_ids: &[ComponentID],
+    ) -> ArchetypeID {
+        let src_arche = unsafe { self.get(src_arche_id) };
+
+        let target_comp_bitset = {
+            let mut bitset = src_arche.component_id_bitset.clone();
+            for &comp_id in old_comp_ids {
+                bitset.clear(comp_id);
+            }
+            bitset
+        };
+
+        if let Some(&dst_arche_id) = self.ids.get(&target_comp_bitset) {
+            return dst_arche_id;
+        }
+
+        // Archetype without the components did not exist, so create it
+        let dst_arche_id = self.create_archetype(target_comp_bitset);
+        // SAFETY: Archetypes are guaranteed to exist and be unique, so we can safely get mutable references
+        let (src_arche, dst_arche) =
+            unsafe { get_two_mut_unchecked(&mut self.archetype_table, src_arche_id, dst_arche_id) };
+
+        // add the components storages, inherited from the src archetype (except those to remove)
+        for comp_storage in src_arche.components.values() {
+            if old_comp_ids.contains(&comp_storage.id()) {
+                continue;
+            }
+
+            dst_arche.components.insert(
+                comp_storage.id(),
+                ComponentStorage::from_other(comp_storage),
+            );
+        }
+
         dst_arche_id
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use crate::World;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(u32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(u32);
+    #[derive(Debug, PartialEq)]
+    struct Mass(u32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register_component::<Position>();
+        world.register_component::<Velocity>();
+        world.register_component::<Mass>();
+        world
+    }
+
+    #[test]
+    fn spawn_bundle() {
+        let mut world = world();
+
+        let a = world.spawn((Position(1), Velocity(2)));
+        let b = world.spawn((Velocity(3), Position(4)));
+
+        // Same set of components in a different order resolves to the same archetype
+        let record_a = unsafe { world.entity_manager.get_record(a) };
+        let record_b = unsafe { world.entity_manager.get_record(b) };
+        assert_eq!(record_a.archetype_id, record_b.archetype_id);
+
+        assert_eq!(world.get_component::<Position>(a), Some(&Position(1)));
+        assert_eq!(world.get_component::<Velocity>(b), Some(&Velocity(3)));
+        assert!(!world.has_component::<Mass>(a));
+    }
+
+    #[test]
+    fn insert_and_remove_bundle() {
+        let mut world = world();
+
+        let entity = world.spawn((Position(1),));
+        let other = world.spawn((Position(0),));
+        world.insert_bundle(entity, (Position(2), Velocity(3), Mass(4)));
+
+        assert_eq!(world.get_component::<Position>(entity), Some(&Position(2)));
+        assert_eq!(world.get_component::<Velocity>(entity), Some(&Velocity(3)));
+        assert_eq!(world.get_component::<Mass>(entity), Some(&Mass(4)));
+
+        world.remove_bundle::<(Velocity, Mass)>(entity);
+
+        assert_eq!(world.get_component::<Position>(entity), Some(&Position(2)));
+        assert!(!world.has_component::<Velocity>(entity));
+        assert!(!world.has_component::<Mass>(entity));
+
+        // The entity that was swapped into the vacated row is unaffected
+        assert_eq!(world.get_component::<Position>(other), Some(&Position(0)));
+    }
+
+    #[test]
+    fn spawn_batch() {
+        let mut world = world();
+
+        let first = world.spawn((Position(0), Velocity(0)));
+        let entities = world.spawn_batch((1..=100).map(|i| (Position(i), Velocity(i * 2))));
+
+        assert_eq!(entities.len(), 100);
+        for (i, &entity) in (1..=100).zip(entities.iter()) {
+            assert_eq!(world.get_component::<Position>(entity), Some(&Position(i)));
+            assert_eq!(
+                world.get_component::<Velocity>(entity),
+                Some(&Velocity(i * 2))
+            );
+        }
+
+        let record_first = unsafe { world.entity_manager.get_record(first) };
+        let record_last = unsafe { world.entity_manager.get_record(entities[99]) };
+        assert_eq!(record_first.archetype_id, record_last.archetype_id);
+        assert_eq!(record_last.archetype_row, 100);
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_component_in_bundle() {
+        let mut world = world();
+        world.spawn((Position(1), Position(2)));
+    }
+
+    #[test]
+    fn invalid_bundle_creates_no_entity() {
+        let mut world = world();
+        let before = world.spawn((Position(0),));
+
+        let spawn = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.spawn((Position(1), Position(2)));
+        }));
+        assert!(spawn.is_err());
+
+        let batch = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            world.spawn_batch([(Velocity(1), Velocity(2))]);
+        }));
+        assert!(batch.is_err());
+
+        // No entity was left behind by the failed spawns, and deleting the next one leaves the others in place
+        let after = world.spawn((Position(3),));
+        assert_eq!(after.index(), before.index() + 1);
+        world.delete_entity(after);
+        assert_eq!(world.get_component::<Position>(before), Some(&Position(0)));
+    }
+
+    #[test]
+    fn tracking_survives_moves() {
+        let mut world = world();
+
+        let entity = world.spawn((Position(1),));
+        let record = unsafe { world.entity_manager.get_record(entity) };
+        let arche = unsafe { world.archetype_manager.get_mut(record.archetype_id) };
+        unsafe { arche.get_mut_storage(0).enable_tracking() };
+
+        world.tick = 5;
+        let other = world.spawn((Position(2),));
+        world.tick = 8;
+        world.add_component(entity, Velocity(3));
+        world.add_component(other, Velocity(4));
+
+        // Both moved into an archetype that is tracked as well, keeping the tick they were added at
+        let record = unsafe { world.entity_manager.get_record(other) };
+        let arche = unsafe { world.archetype_manager.get(record.archetype_id) };
+        let storage = unsafe { arche.get_storage(0) };
+        assert!(storage.is_tracked());
+
+        let tracker = unsafe { storage.get_tracker() };
+        let entity_row = unsafe { world.entity_manager.get_record(entity).archetype_row };
+        assert_eq!(unsafe { tracker.get(entity_row) }.modified, 0);
+        assert_eq!(unsafe { tracker.get(record.archetype_row) }.modified, 5);
+        assert_eq!(tracker.last_write(), 5);
+    }
 }
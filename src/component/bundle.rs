@@ -0,0 +1,143 @@
+use crate::{archetype::Archetype, util::all_tuples};
+
+use super::{Component, ComponentID, ComponentManager};
+
+/// A Bundle is a tuple of components that are inserted into, or removed from, an entity together.
+///
+/// Unlike adding components one at a time, which moves the entity along a single archetype graph edge
+/// per component, the archetype that the entity ends up in is resolved for the bundle as a whole, so
+/// the entity's existing component data is only moved once.
+///
+/// Each component type may only appear once within a bundle.
+pub trait Bundle: 'static {
+    /// The component IDs of the bundle, in the order they appear in the tuple
+    type Id: AsRef<[ComponentID]> + Copy;
+
+    /// Returns the component IDs for the components in the bundle
+    ///
+    /// # Panics
+    /// - If any component type has not been registered
+    /// - If the same component type appears more than once
+    fn parameter_ids(component_manager: &ComponentManager) -> Self::Id;
+
+    /// Pushes each component onto the end of its storage within the archetype, counting them as modified at
+    /// the given world tick
+    ///
+    /// # Safety
+    /// - The archetype must contain a storage for every component in the bundle.
+    unsafe fn push_components(self, archetype: &mut Archetype, id: &Self::Id, tick: u32);
+
+    /// Overwrites each component at the given row of its storage within the archetype, dropping the
+    /// previous value, and counting them as modified at the given world tick
+    ///
+    /// # Safety
+    /// - The archetype must contain a storage for every component in the bundle.
+    /// - The row must be within the bounds of the storages.
+    unsafe fn replace_components(
+        self,
+        archetype: &mut Archetype,
+        id: &Self::Id,
+        row: usize,
+        tick: u32,
+    );
+
+    /// Writes each component at the given row of its storage within the archetype. Components whose
+    /// storage already has that row are overwritten, as with [Bundle::replace_components], while the rest
+    /// are pushed onto the end, as with [Bundle::push_components].
+    ///
+    /// # Safety
+    /// - The archetype must contain a storage for every component in the bundle.
+    /// - The length of each of those storages must be either the row, or greater than it.
+    unsafe fn insert_components(
+        self,
+        archetype: &mut Archetype,
+        id: &Self::Id,
+        row: usize,
+        tick: u32,
+    );
+}
+
+impl Bundle for () {
+    type Id = [ComponentID; 0];
+
+    fn parameter_ids(_component_manager: &ComponentManager) -> Self::Id {
+        []
+    }
+
+    unsafe fn push_components(self, _archetype: &mut Archetype, _id: &Self::Id, _tick: u32) {}
+
+    unsafe fn replace_components(
+        self,
+        _archetype: &mut Archetype,
+        _id: &Self::Id,
+        _row: usize,
+        _tick: u32,
+    ) {
+    }
+
+    unsafe fn insert_components(
+        self,
+        _archetype: &mut Archetype,
+        _id: &Self::Id,
+        _row: usize,
+        _tick: u32,
+    ) {
+    }
+}
+
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn duplicate_component() -> ! {
+    panic!("Bundle contains the same component type more than once");
+}
+
+macro_rules! impl_bundle {
+    ($($C:ident $i:tt),+) => {
+        impl<$($C: Component),+> Bundle for ($($C,)+) {
+            type Id = [ComponentID; [$($i),+].len()];
+
+            fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
+                let ids = [$(component_manager.get_id::<$C>()),+];
+
+                for (i, id) in ids.iter().enumerate() {
+                    if ids[i + 1..].contains(id) {
+                        duplicate_component();
+                    }
+                }
+
+                ids
+            }
+
+            unsafe fn push_components(self, archetype: &mut Archetype, id: &Self::Id, tick: u32) {
+                // SAFETY: Caller ensures that the archetype contains each component
+                $(unsafe { archetype.push_component(id[$i], self.$i, tick) };)+
+            }
+
+            unsafe fn replace_components(
+                self,
+                archetype: &mut Archetype,
+                id: &Self::Id,
+                row: usize,
+                tick: u32,
+            ) {
+                // SAFETY: Caller ensures that the archetype contains each component, and the row is valid
+                $(unsafe { archetype.replace_component(id[$i], row, self.$i, tick) };)+
+            }
+
+            unsafe fn insert_components(
+                self,
+                archetype: &mut Archetype,
+                id: &Self::Id,
+                row: usize,
+                tick: u32,
+            ) {
+                // SAFETY: Caller ensures that the archetype contains each component, and the row is valid
+                //         for it or one past the end
+                $(unsafe { archetype.insert_component(id[$i], row, self.$i, tick) };)+
+            }
+        }
+    };
+}
+
+all_tuples!(impl_bundle, C0 0, C1 1, C2 2, C3 3, C4 4, C5 5, C6 6, C7 7, C8 8, C9 9, C10 10, C11 11, C12 12, C13 13, C14 14, C15 15);
@@ -6,6 +6,8 @@ use std::collections::HashMap;
 
 use collections::Ptr;
 
+use crate::{entity::Entity, World};
+
 /// Unique sequential integer
 pub type ComponentID = usize;
 
@@ -18,6 +20,8 @@ pub struct ComponentManager {
     /// Stores the metadata for each component type, accessible using the component id
     /// as the index
     metadata: Vec<ComponentMetaData>,
+
+    has_hooks: bool,
 }
 
 impl ComponentManager {
@@ -25,19 +29,45 @@ impl ComponentManager {
         Self {
             ids: HashMap::with_capacity_and_hasher(8, nohash_hasher::BuildNoHashHasher::default()),
             metadata: Vec::with_capacity(8),
+            has_hooks: false,
         }
     }
 
-    /// Registers a component type with the component manager
-    pub fn register<C: Component>(&mut self) {
+    /// Registers a component type with the component manager, returning its ID. Registering a type more
+    /// than once has no effect.
+    pub fn register<C: Component>(&mut self) -> ComponentID {
         let type_id = C::type_id();
-        if self.ids.contains_key(&type_id) {
-            return;
+        if let Some(&comp_id) = self.ids.get(&type_id) {
+            return comp_id;
         }
 
         let comp_id = self.ids.len();
         self.ids.insert(type_id, comp_id);
         self.metadata.push(ComponentMetaData::new::<C>());
+        comp_id
+    }
+
+    /// Registers a component type with the component manager, along with hooks that are run when the
+    /// component is added to, or removed from, an entity. If the type is already registered, its hooks are
+    /// replaced.
+    pub fn register_with_hooks<C: Component>(&mut self, hooks: ComponentHooks) -> ComponentID {
+        let comp_id = self.register::<C>();
+        self.metadata[comp_id].hooks = hooks;
+        self.has_hooks |= !hooks.is_empty();
+        comp_id
+    }
+
+    /// Registers a component type with the component manager, such that every storage of it is tracked
+    /// from creation. If the type is already registered, only storages created from now on are affected.
+    pub fn register_tracked<C: Component>(&mut self) -> ComponentID {
+        let comp_id = self.register::<C>();
+        self.metadata[comp_id].tracked = true;
+        comp_id
+    }
+
+    /// Returns true if any component type has a hook, so that looking for them can be skipped otherwise
+    pub fn has_hooks(&self) -> bool {
+        self.has_hooks
     }
 
     /// Returns the component id for the given component type
@@ -71,6 +101,10 @@ pub struct ComponentMetaData {
     pub type_id: TypeId,
     pub layout: Layout,
     pub drop: unsafe fn(Ptr),
+    pub hooks: ComponentHooks,
+    /// Whether every storage of the component type is tracked from creation, rather than once a query
+    /// that tracks it matches the archetype
+    pub tracked: bool,
 }
 
 impl ComponentMetaData {
@@ -79,17 +113,67 @@ impl ComponentMetaData {
             type_id: T::type_id(),
             layout: Layout::new::<T>(),
             drop: |ptr: Ptr| unsafe { ptr.drop_as::<T>() },
+            hooks: ComponentHooks::new(),
+            tracked: false,
         }
     }
 }
 
-pub trait Component: 'static {
+/// A function that is run with the entity that a component has been added to, or is about to be removed
+/// from
+pub type ComponentHook = fn(&mut World, Entity);
+
+/// Hooks for the lifecycle of a component type, which are run by the [World] when it changes the
+/// components of an entity, including through commands.
+///
+/// - `on_add` is run after the component is added to an entity that did not have it
+/// - `on_insert` is run after the component is added to an entity, or replaces one that it already had
+/// - `on_remove` is run before the component is removed from an entity, including when the entity is
+///   deleted, so the component can still be read
+///
+/// Where several components change at once, such as with a [Bundle](super::bundle::Bundle), every
+/// `on_add` hook is run before any `on_insert` hook. Hooks stop being run for an entity if one of them
+/// deletes it.
+#[derive(Clone, Copy, Default)]
+pub struct ComponentHooks {
+    pub on_add: Option<ComponentHook>,
+    pub on_insert: Option<ComponentHook>,
+    pub on_remove: Option<ComponentHook>,
+}
+
+impl ComponentHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_add(mut self, hook: ComponentHook) -> Self {
+        self.on_add = Some(hook);
+        self
+    }
+
+    pub fn on_insert(mut self, hook: ComponentHook) -> Self {
+        self.on_insert = Some(hook);
+        self
+    }
+
+    pub fn on_remove(mut self, hook: ComponentHook) -> Self {
+        self.on_remove = Some(hook);
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.on_add.is_none() && self.on_insert.is_none() && self.on_remove.is_none()
+    }
+}
+
+/// Components must be [Send] and [Sync], as systems that access them may be run on other threads
+pub trait Component: Send + Sync + 'static {
     /// Returns the type id of the component type
     fn type_id() -> TypeId {
         TypeId::of::<Self>()
     }
 }
-impl<T: Any> Component for T {}
+impl<T: Any + Send + Sync> Component for T {}
 
 #[cfg(test)]
 mod tests {
@@ -117,7 +201,7 @@ mod tests {
         manager.register::<CompB>();
 
         let mut storage = ComponentStorage::new::<CompA>(0);
-        unsafe { storage.push(42) };
+        unsafe { storage.push(42, 0) };
 
         assert_eq!(unsafe { storage.get::<CompA>(0) }, &42);
     }
@@ -129,7 +213,7 @@ mod tests {
         manager.register::<CompB>();
 
         let mut storage = ComponentStorage::new::<CompA>(0);
-        unsafe { storage.push(42) };
+        unsafe { storage.push(42, 0) };
         unsafe { storage.delete(0) };
 
         assert_eq!(storage.len(), 0);
@@ -142,12 +226,55 @@ mod tests {
         manager.register::<CompB>();
 
         let mut storage = ComponentStorage::new::<CompA>(0);
-        unsafe { storage.push(42) };
+        unsafe { storage.push(42, 0) };
 
         let mut other = ComponentStorage::new::<CompA>(1);
-        unsafe { storage.transfer(0, &mut other) };
+        unsafe { storage.transfer(0, &mut other, 0) };
 
         assert_eq!(storage.len(), 0);
         assert_eq!(unsafe { other.get::<CompA>(0) }, &42);
     }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    struct Handle(&'static str);
+
+    fn log(world: &mut World, entry: &'static str) {
+        let log_id = world.get_resource_id::<Log>();
+        let log = unsafe { world.get_mut_resource_unchecked(log_id) };
+        log.0.push(entry);
+    }
+
+    #[test]
+    fn lifecycle_hooks() {
+        let mut world = World::new();
+        let log_id = world.add_resource(Log::default());
+        world.register_component::<CompA>();
+        world.register_component_with_hooks::<Handle>(
+            ComponentHooks::new()
+                .on_add(|world, _| log(world, "add"))
+                .on_insert(|world, _| log(world, "insert"))
+                .on_remove(|world, entity| {
+                    // The component can still be read, to release whatever it holds
+                    let name = world.get_component::<Handle>(entity).unwrap().0;
+                    log(world, name);
+                }),
+        );
+
+        let entity = world.create_entity();
+        world.add_component(entity, Handle("first"));
+        world.insert_bundle(entity, (Handle("second"), 1 as CompA));
+        world.remove_component::<Handle>(entity);
+        assert!(!world.has_component::<Handle>(entity));
+
+        let other = world.spawn((Handle("third"),));
+        world.remove_bundle::<(CompA,)>(other);
+        world.delete_entity(other);
+
+        assert_eq!(
+            world.get_resource(log_id).unwrap().0,
+            vec!["add", "insert", "insert", "second", "add", "insert", "third"]
+        );
+    }
 }
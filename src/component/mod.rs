@@ -1,5 +1,7 @@
 mod component;
 pub use component::*;
 
+pub mod bundle;
+pub mod removed;
 pub mod storage;
-pub mod tracking;
\ No newline at end of file
+pub mod tracking;
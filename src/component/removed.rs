@@ -0,0 +1,167 @@
+use core::marker::PhantomData;
+
+use crate::{
+    entity::Entity,
+    event::{EventReader, Events},
+    system::{
+        command::CommandQueue,
+        param::{SystemMeta, SystemParam},
+    },
+    World,
+};
+
+use super::{Component, ComponentID};
+
+/// Records the entities that have lost a component, either because it was removed or because the entity
+/// was deleted.
+///
+/// # Implementation
+/// The entities are kept as [Events], one list per component type indexed by component ID, so they are
+/// held for two updates and each reader can keep its own cursor.
+pub struct RemovedComponentEvents {
+    events: Vec<Events<Entity>>,
+}
+
+impl RemovedComponentEvents {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    /// Makes sure there is a list for the component, so that it can be borrowed while the world is shared
+    pub fn ensure(&mut self, comp_id: ComponentID) -> &mut Events<Entity> {
+        if comp_id >= self.events.len() {
+            self.events.resize_with(comp_id + 1, Events::new);
+        }
+
+        &mut self.events[comp_id]
+    }
+
+    pub fn record(&mut self, comp_id: ComponentID, entity: Entity) {
+        self.ensure(comp_id).push(entity);
+    }
+
+    pub fn get(&self, comp_id: ComponentID) -> Option<&Events<Entity>> {
+        self.events.get(comp_id)
+    }
+
+    /// Swaps the buffers of every list, in the same way as registered events
+    pub fn clear(&mut self) {
+        for events in self.events.iter_mut() {
+            events.clear();
+        }
+    }
+}
+
+/// A system parameter that provides the entities that have lost a component of type `T`, either because
+/// it was removed or because the entity was deleted, since the system last ran. As with an
+/// [EventReader], removals are only held for two updates.
+///
+/// The entities may have since been deleted, or had the component added again.
+pub struct RemovedComponents<'w, 's, T: Component> {
+    reader: EventReader<'w, 's, Entity>,
+    _marker: PhantomData<T>,
+}
+
+impl<'w, T: Component> RemovedComponents<'w, '_, T> {
+    /// Iterates over the entities that this system has not yet seen lose the component
+    pub fn iter(&mut self) -> impl Iterator<Item = Entity> + 'w {
+        self.reader.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reader.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reader.is_empty()
+    }
+
+    /// Marks every removal as seen, without reading them
+    pub fn clear(&mut self) {
+        self.reader.clear();
+    }
+}
+
+// Removals are only recorded while the world is borrowed mutably, between systems, so there is no access
+// to add
+impl<T: Component> SystemParam for RemovedComponents<'_, '_, T> {
+    type State = (ComponentID, usize);
+    type Item<'w, 's> = RemovedComponents<'w, 's, T>;
+
+    /// # Panics
+    /// - If the component type has not been registered
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        let comp_id = world.component_manager.get_id::<T>();
+        world.removed_components.ensure(comp_id);
+        (comp_id, 0)
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        let (comp_id, cursor) = state;
+
+        // SAFETY: The list was created when the state was initialised, and lists are never removed
+        let events = unsafe { world.removed_components.get(*comp_id).unwrap_unchecked() };
+
+        RemovedComponents {
+            reader: EventReader::new(events, cursor),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::system::{param::Commands, schedule::ScheduleBuilder};
+
+    use super::*;
+
+    struct Handle;
+
+    #[derive(Default)]
+    struct Released(Vec<Entity>);
+
+    #[test]
+    fn removals_and_deletions() {
+        let mut world = World::new();
+        world.register_component::<Handle>();
+        let released_id = world.add_resource(Released::default());
+
+        let entities = world.spawn_batch([(Handle,), (Handle,), (Handle,)]);
+        let cleanup = |mut removed: RemovedComponents<Handle>, released: &mut Released| {
+            released.0.extend(removed.iter());
+        };
+        let last = entities[2];
+        let delete_last = move |mut commands: Commands| {
+            commands.remove_entity(last);
+        };
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .add(cleanup)
+                .add(delete_last)
+                .build()
+                .unwrap(),
+        );
+
+        world.remove_component::<Handle>(entities[0]);
+        world.delete_entity(entities[1]);
+        world.update();
+        assert_eq!(
+            world.get_resource(released_id).unwrap().0,
+            vec![entities[0], entities[1]]
+        );
+
+        // The deletion by command happened after the cleanup system ran, so is picked up on the next
+        // update, and earlier removals are not repeated
+        world.update();
+        world.update();
+        assert_eq!(
+            world.get_resource(released_id).unwrap().0,
+            vec![entities[0], entities[1], entities[2]]
+        );
+    }
+}
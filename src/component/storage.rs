@@ -3,7 +3,7 @@ use core::{cell::UnsafeCell, mem::ManuallyDrop};
 use collections::{ErasedType, ErasedVec, Ptr};
 
 use super::{
-    tracking::{ChangeTracking, TrackingInfo},
+    tracking::{oldest_tick, ChangeTicks, ChangeTracking, TrackingInfo},
     Component, ComponentID, ComponentMetaData,
 };
 
@@ -23,6 +23,13 @@ pub struct ComponentStorage {
     tracker: Option<ChangeTracking>,
 }
 
+// SAFETY: Only Components can be stored, which are themselves Send and Sync. Mutable access through a
+//         shared reference is only given out by unsafe methods, whose callers must ensure it is not
+//         aliased. Systems record which components they write to, so that those running in parallel
+//         never do so.
+unsafe impl Send for ComponentStorage {}
+unsafe impl Sync for ComponentStorage {}
+
 impl ComponentStorage {
     pub fn new<C: Component>(id: ComponentID) -> Self {
         Self {
@@ -32,13 +39,14 @@ impl ComponentStorage {
         }
     }
 
+    /// Creates an empty storage for the same component type, which is tracked if the other one is
     pub fn from_other(other: &Self) -> Self {
         let erased_type = other.components.erased_type().clone();
 
         Self {
             id: other.id,
             components: ErasedVec::from_erased_type(erased_type),
-            tracker: None,
+            tracker: other.tracker.as_ref().map(|_| ChangeTracking::new()),
         }
     }
 
@@ -49,7 +57,7 @@ impl ComponentStorage {
         Self {
             id,
             components: ErasedVec::from_erased_type(erased_type),
-            tracker: None,
+            tracker: metadata.tracked.then(ChangeTracking::new),
         }
     }
 
@@ -61,12 +69,29 @@ impl ComponentStorage {
         self.components.len()
     }
 
+    /// Reserves capacity for at least `additional` more components, to avoid repeated reallocation
+    /// when many components are about to be pushed.
+    pub fn reserve(&mut self, additional: usize) {
+        self.components.reserve(additional);
+
+        if let Some(tracker) = self.tracker.as_mut() {
+            tracker.reserve(additional);
+        }
+    }
+
     pub fn enable_tracking(&mut self) {
         if let None = self.tracker {
             self.tracker = Some(ChangeTracking::with_len(self.components.len()));
         }
     }
 
+    /// Clamps every tick that is becoming too old to be compared with the current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        if let Some(tracker) = self.tracker.as_mut() {
+            tracker.check_ticks(this_run);
+        }
+    }
+
     pub fn is_tracked(&self) -> bool {
         self.tracker.is_some()
     }
@@ -85,9 +110,11 @@ impl ComponentStorage {
         unsafe { self.tracker.as_mut().unwrap_unchecked() }
     }
 
+    /// Pushes the component, which counts as added and modified at the given world tick
+    ///
     /// # Safety
     /// - The generic type parameter must match the underlying type of this component storage.
-    pub unsafe fn push<C: Component>(&mut self, component: C) {
+    pub unsafe fn push<C: Component>(&mut self, component: C, tick: u32) {
         let mut component = ManuallyDrop::new(component);
         let comp_ptr = Ptr::from(&mut component);
 
@@ -96,12 +123,25 @@ impl ComponentStorage {
 
         if self.is_tracked() {
             let tracker = self.get_mut_tracker();
+            tracker.push(TrackingInfo::new(tick));
+            tracker.set_last_write(tick);
+        }
+    }
 
-            // TODO: we need to get current world tick to update last_write below
-            let tick = 0;
+    /// Overwrites the component at the given index, dropping the previous value. The new value counts as
+    /// modified at the given world tick.
+    ///
+    /// # Safety
+    /// - The index must be within the bounds of the underlying vec.
+    /// - The generic type parameter must match the underlying type of this component storage.
+    pub unsafe fn replace<C: Component>(&mut self, index: usize, component: C, tick: u32) {
+        // SAFETY: Deferred to the caller
+        unsafe { *self.get_mut(index) = component };
 
-            tracker.push(TrackingInfo::new(tick));
-            tracker.last_write = tick;
+        if self.is_tracked() {
+            let tracker = self.get_mut_tracker();
+            unsafe { tracker.get_mut(index).modified = tick };
+            tracker.set_last_write(tick);
         }
     }
 
@@ -148,10 +188,14 @@ impl ComponentStorage {
         }
     }
 
+    /// Moves the component to the end of the destination storage, along with its tracking info. If this
+    /// storage is not tracked but the destination is, there is no way of knowing when the component was
+    /// added or modified, so it counts as the oldest that can be compared with the given world tick.
+    ///
     /// # Safety
     /// - The `src_index` must be within the bounds of the underlying source vec.
     /// - The underlying component type of the source and destination component storage must match.
-    pub unsafe fn transfer(&mut self, src_index: usize, dst: &mut Self) {
+    pub unsafe fn transfer(&mut self, src_index: usize, dst: &mut Self, tick: u32) {
         debug_assert!(src_index < self.len());
 
         // SAFETY: Bounds and type check deferred to the caller.
@@ -160,19 +204,18 @@ impl ComponentStorage {
             dst.components.push(ptr);
         }
 
-        if self.is_tracked() {
-            let tracker = self.get_mut_tracker();
-            tracker.delete(src_index);
-        }
-
-        if dst.is_tracked() {
-            let tracker = dst.get_mut_tracker();
-
-            // TODO: we need to get current world tick to update last_write below
-            let tick = 0;
-
-            tracker.push(TrackingInfo::new(tick));
-            tracker.last_write = tick;
+        let info = self
+            .tracker
+            .as_mut()
+            .map(|tracker| tracker.delete(src_index));
+
+        if let Some(tracker) = dst.tracker.as_mut() {
+            let info = info.unwrap_or_else(|| TrackingInfo::new(oldest_tick(tick)));
+            // Ticks wrap around, so the newer of the two is the one closer to the current tick
+            if ChangeTicks::new(tracker.last_write(), tick).is_newer(info.modified) {
+                tracker.set_last_write(info.modified);
+            }
+            tracker.push(info);
         }
     }
 
@@ -1,72 +1,213 @@
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// The most ticks that can pass between checks for ticks that are becoming too old to compare
+pub const CHECK_TICK_INTERVAL: u32 = 1 << 29;
+
+/// The oldest that a tick can be, relative to the world tick, before it is clamped.
+///
+/// Ticks are compared by how long ago they were, rather than by value, so that comparisons still work
+/// once the world tick wraps around. This is unambiguous as long as no tick is ever older than
+/// `u32::MAX`, and ticks can keep aging for up to [CHECK_TICK_INTERVAL] between checks.
+pub const MAX_CHANGE_AGE: u32 = u32::MAX - 2 * CHECK_TICK_INTERVAL;
+
+/// Returns the oldest tick that can still be compared with the current one. Older ticks are clamped to
+/// this.
+pub fn oldest_tick(this_run: u32) -> u32 {
+    this_run.wrapping_sub(MAX_CHANGE_AGE)
+}
+
+/// Clamps the tick, so that it is no older than [MAX_CHANGE_AGE] relative to the current one
+pub fn clamp_tick(tick: &mut u32, this_run: u32) {
+    if this_run.wrapping_sub(*tick) > MAX_CHANGE_AGE {
+        *tick = oldest_tick(this_run);
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct TrackingInfo {
+    /// The world tick at which the component was added to the entity
+    pub added: u32,
+    /// The world tick at which the component was last modified, which includes being added
     pub modified: u32,
 }
 
 impl TrackingInfo {
-    pub fn new(modified: u32) -> Self {
-        Self { modified }
+    /// Info for a component that was added at the given tick
+    pub fn new(added: u32) -> Self {
+        Self {
+            added,
+            modified: added,
+        }
     }
 }
 
 pub struct ChangeTracking {
     /// The length of this will always match the length of the component storage's vec.
     /// It stores the world tick at which various things occurred to the component.
-    info: Vec<TrackingInfo>,
-
-    /// This is updated after a system has run for a set of components.
     ///
-    /// When a system runs for a set of components, we cannot guarantee whether each one has been read, so
-    /// we store the read tick for the entire set. But, we *can* know when a component has been written to,
-    /// with user submitted commands, so we store the write tick for each component and compare them to
-    /// detect changes.
-    pub(crate) last_read: u32,
-
-    /// This is updated whenever a new component is added, or when the user issues a `FlagModifiedCommand`
-    /// for a component.
+    /// Each is in a cell, so that a [Mut] can stamp it while the storage is only borrowed immutably.
+    info: Vec<UnsafeCell<TrackingInfo>>,
+
+    /// This is updated whenever a new component is added, or modified through a [Mut] or a
+    /// `FlagModifiedCommand`.
     ///
-    /// This is the tick of when the last modification to a component occurred.
-    pub(crate) last_write: u32,
+    /// This is the tick of when the last modification to a component occurred. It is atomic, as a [Mut]
+    /// can update it from many threads at once while iterating in parallel.
+    last_write: AtomicU32,
 }
 
 impl ChangeTracking {
     pub fn new() -> Self {
         Self {
             info: Vec::new(),
-            last_read: 0,
-            last_write: 0,
+            last_write: AtomicU32::new(0),
         }
     }
 
     pub fn with_len(len: usize) -> Self {
         Self {
-            info: vec![TrackingInfo::default(); len],
-            last_read: 0,
-            last_write: 0,
+            info: (0..len).map(|_| UnsafeCell::default()).collect(),
+            last_write: AtomicU32::new(0),
         }
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.info.reserve(additional);
+    }
+
     pub fn push(&mut self, info: TrackingInfo) {
-        self.info.push(info);
+        self.info.push(UnsafeCell::new(info));
     }
 
     /// # Safety
     /// - The index must be within the bounds of the underlying vec.
+    /// - The info must not be borrowed mutably through a [Mut].
     pub unsafe fn get(&self, index: usize) -> &TrackingInfo {
         debug_assert!(index < self.info.len());
-        unsafe { self.info.get_unchecked(index) }
+        unsafe { &*self.info.get_unchecked(index).get() }
     }
 
     /// # Safety
     /// - The index must be within the bounds of the underlying vec.
     pub unsafe fn get_mut(&mut self, index: usize) -> &mut TrackingInfo {
         debug_assert!(index < self.info.len());
-        unsafe { self.info.get_unchecked_mut(index) }
+        unsafe { self.info.get_unchecked_mut(index).get_mut() }
+    }
+
+    /// # Safety
+    /// - The index must be within the bounds of the underlying vec.
+    pub unsafe fn get_unsafe_cell(&self, index: usize) -> &UnsafeCell<TrackingInfo> {
+        debug_assert!(index < self.info.len());
+        unsafe { self.info.get_unchecked(index) }
     }
 
+    /// Removes the info at the index, replacing it with the last one, and returns it
+    ///
     /// # Panics
     /// Panics if the index is out of bounds.
-    pub fn delete(&mut self, index: usize) {
-        self.info.swap_remove(index);
+    pub fn delete(&mut self, index: usize) -> TrackingInfo {
+        self.info.swap_remove(index).into_inner()
+    }
+
+    /// Clamps every tick that is becoming too old to be compared with the current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        for info in self.info.iter_mut() {
+            let info = info.get_mut();
+            clamp_tick(&mut info.added, this_run);
+            clamp_tick(&mut info.modified, this_run);
+        }
+
+        clamp_tick(self.last_write.get_mut(), this_run);
+    }
+
+    /// The tick of when the last modification to any component occurred
+    pub fn last_write(&self) -> u32 {
+        self.last_write.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_write(&self, tick: u32) {
+        self.last_write.store(tick, Ordering::Relaxed);
+    }
+}
+
+/// The ticks that changes are detected relative to, while fetching components for a system.
+///
+/// Each system keeps its own `last_run`, so that any number of systems will each see every change once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChangeTicks {
+    /// The world tick at which the system last ran. Components changed after it count as changed.
+    pub last_run: u32,
+    /// The current world tick, which components modified through a [Mut] are stamped with
+    pub this_run: u32,
+}
+
+impl ChangeTicks {
+    pub fn new(last_run: u32, this_run: u32) -> Self {
+        Self { last_run, this_run }
+    }
+
+    /// Returns true if something stamped with the tick happened since the system last ran. This still
+    /// holds once the world tick wraps around, as long as old ticks are clamped.
+    pub fn is_newer(&self, tick: u32) -> bool {
+        let age = self.this_run.wrapping_sub(tick);
+        let since_last_run = self.this_run.wrapping_sub(self.last_run);
+        age < since_last_run
+    }
+}
+
+/// Mutable access to a component, as given out by queries for `&mut T`.
+///
+/// If the storage of the component is tracked, mutably dereferencing this records that the component was
+/// modified at the current world tick. Only reading it through [Deref] records nothing.
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    tracker: Option<(&'a mut TrackingInfo, &'a ChangeTracking)>,
+    tick: u32,
+}
+
+impl<'a, T> Mut<'a, T> {
+    pub(crate) fn new(
+        value: &'a mut T,
+        tracker: Option<(&'a mut TrackingInfo, &'a ChangeTracking)>,
+        tick: u32,
+    ) -> Self {
+        Self {
+            value,
+            tracker,
+            tick,
+        }
+    }
+
+    /// Records the component as modified, and returns the mutable reference with the full lifetime
+    pub fn into_inner(mut self) -> &'a mut T {
+        self.set_modified();
+        self.value
+    }
+
+    /// Records the component as modified, without changing it
+    pub fn set_modified(&mut self) {
+        if let Some((info, tracker)) = self.tracker.as_mut() {
+            info.modified = self.tick;
+            tracker.set_last_write(self.tick);
+        }
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.set_modified();
+        self.value
     }
 }
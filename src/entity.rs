@@ -1,8 +1,54 @@
-use collections::{Store, StoreKey};
+use core::fmt;
 
 use crate::archetype::ArchetypeID;
 
-pub type Entity = u32;
+/// A handle to an entity within a world.
+///
+/// The index locates the entity's record, and the generation is that of the record's slot at the
+/// time the entity was created. Slots are reused after an entity is deleted, but their generation
+/// is bumped when this happens, so a handle to a deleted entity will never be considered alive again.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub const fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    #[inline]
+    pub const fn index(self) -> u32 {
+        self.index
+    }
+
+    #[inline]
+    pub const fn generation(self) -> u32 {
+        self.generation
+    }
+
+    /// Encodes the entity as a single integer, with the generation in the upper 32 bits and the index
+    /// in the lower 32 bits. This encoding is stable and can be reversed with [Entity::from_bits].
+    #[inline]
+    pub const fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    #[inline]
+    pub const fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+impl fmt::Debug for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
 
 #[derive(Default)]
 pub struct EntityRecord {
@@ -10,14 +56,25 @@ pub struct EntityRecord {
     pub archetype_row: usize,
 }
 
+#[derive(Default)]
+struct EntitySlot {
+    generation: u32,
+    alive: bool,
+    record: EntityRecord,
+}
+
 pub struct EntityManager {
-    records: Store<EntityRecord>,
+    slots: Vec<EntitySlot>,
+
+    /// Indices of slots whose entity has been deleted, so can be reused by the next created entity
+    free: Vec<u32>,
 }
 
 impl EntityManager {
     pub fn new() -> Self {
         EntityManager {
-            records: Store::with_capacity(32),
+            slots: Vec::with_capacity(32),
+            free: Vec::new(),
         }
     }
 
@@ -27,8 +84,9 @@ impl EntityManager {
     /// # Safety
     /// - Entity must be alive
     pub unsafe fn get_record(&self, entity: Entity) -> &EntityRecord {
+        debug_assert!(self.alive(entity));
         // SAFETY: If the entity is alive, the index is valid and the generation is current.
-        unsafe { self.records.get_unchecked(StoreKey::from_key(entity)) }
+        unsafe { &self.slots.get_unchecked(entity.index as usize).record }
     }
 
     /// Returns the entity record at the index associated with this entity, regardless of whether
@@ -37,19 +95,82 @@ impl EntityManager {
     /// # Safety
     /// - Entity must be alive
     pub unsafe fn get_record_mut_unchecked(&mut self, entity: Entity) -> &mut EntityRecord {
+        debug_assert!(self.alive(entity));
         // SAFETY: If the entity is alive, the index is valid and the generation is current.
-        unsafe { self.records.get_mut_unchecked(StoreKey::from_key(entity)) }
+        unsafe { &mut self.slots.get_unchecked_mut(entity.index as usize).record }
+    }
+
+    /// Reserves capacity for at least `additional` more entities to be created, taking into account
+    /// slots that are available for reuse.
+    pub fn reserve(&mut self, additional: usize) {
+        self.slots
+            .reserve(additional.saturating_sub(self.free.len()));
     }
 
     pub fn create(&mut self) -> Entity {
-        self.records.push(EntityRecord::default()).id()
+        if let Some(index) = self.free.pop() {
+            // SAFETY: Indices are only added to the free list after being taken from a valid slot
+            let slot = unsafe { self.slots.get_unchecked_mut(index as usize) };
+            slot.alive = true;
+            slot.record = EntityRecord::default();
+
+            return Entity::new(index, slot.generation);
+        }
+
+        let index = self.slots.len() as u32;
+        self.slots.push(EntitySlot {
+            generation: 0,
+            alive: true,
+            record: EntityRecord::default(),
+        });
+
+        Entity::new(index, 0)
     }
 
     pub fn alive(&self, entity: Entity) -> bool {
-        self.records.contains_key(StoreKey::from_key(entity))
+        self.slots
+            .get(entity.index as usize)
+            .is_some_and(|slot| slot.alive && slot.generation == entity.generation)
     }
 
     pub fn delete(&mut self, entity: Entity) {
-        self.records.remove(StoreKey::from_key(entity));
+        if !self.alive(entity) {
+            return;
+        }
+
+        // SAFETY: We just checked that the entity is alive, so the index is valid
+        let slot = unsafe { self.slots.get_unchecked_mut(entity.index as usize) };
+        slot.alive = false;
+        slot.generation = slot.generation.wrapping_add(1);
+
+        self.free.push(entity.index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reused_slot_is_not_alive() {
+        let mut manager = EntityManager::new();
+
+        let a = manager.create();
+        manager.delete(a);
+        let b = manager.create();
+
+        assert_eq!(a.index(), b.index());
+        assert_ne!(a.generation(), b.generation());
+        assert!(!manager.alive(a));
+        assert!(manager.alive(b));
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let entity = Entity::new(42, 7);
+
+        assert_eq!(Entity::from_bits(entity.to_bits()), entity);
+        assert_eq!(entity.to_bits(), 7 << 32 | 42);
+        assert_eq!(format!("{:?}", entity), "42v7");
     }
 }
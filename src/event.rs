@@ -1,7 +1,25 @@
-use crate::{resource::ResourceManager, ResourceId};
+use crate::{
+    resource::ResourceManager,
+    system::{
+        access::Access,
+        command::CommandQueue,
+        param::{SystemMeta, SystemParam},
+    },
+    ResourceId, World,
+};
 
+/// Keeps track of every registered event type, so that their buffers can be swapped on each update
+///
+/// # Implementation
+/// Events are stored as resources, so each event type is identified by its resource index. As the type is
+/// erased, each is stored alongside a function, monomorphised for the type, that swaps its buffers.
 pub struct EventManager {
-    event_lists: Vec<usize>,
+    event_lists: Vec<EventList>,
+}
+
+struct EventList {
+    index: usize,
+    clear: fn(&mut ResourceManager, usize),
 }
 
 impl EventManager {
@@ -11,22 +29,51 @@ impl EventManager {
         }
     }
 
-    pub fn register_event<T: 'static>(&mut self, id: ResourceId<T>) {
-        self.event_lists.push(id.index);
+    pub fn register_event<E: Send + Sync + 'static>(&mut self, id: ResourceId<Events<E>>) {
+        self.event_lists.push(EventList {
+            index: id.index,
+            clear: clear_event_list::<E>,
+        });
     }
 
-    pub fn clear_events(&self, resource_manager: &ResourceManager) {
-        // for event_list in &self.event_lists {
-        //     let events = unsafe { resource_manager.get_mut_unchecked(*event_list) };
-        // }
-
-        todo!()
+    /// Swaps the buffers of every registered event type, dropping events that have already been readable
+    /// for a whole update
+    pub fn clear_events(&self, resource_manager: &mut ResourceManager) {
+        for event_list in self.event_lists.iter() {
+            (event_list.clear)(resource_manager, event_list.index);
+        }
     }
 }
 
+fn clear_event_list<E: Send + Sync + 'static>(
+    resource_manager: &mut ResourceManager,
+    index: usize,
+) {
+    // SAFETY:
+    // - The index was taken from a ResourceId for the same event type when it was registered
+    // - The resource manager is borrowed mutably, so the resource is not borrowed anywhere else
+    let events = unsafe { resource_manager.get_mut_unchecked(ResourceId::<Events<E>>::new(index)) };
+    events.clear();
+}
+
+/// A double buffered list of events. Events that are pushed during an update can be read during the next
+/// update, after which they are dropped. This means that every system gets to read each event exactly
+/// once, regardless of whether it runs before or after the system that pushed it.
+///
+/// Systems that need to read events as soon as they are pushed, or that run several times per update,
+/// should use an [EventReader] instead, which keeps track of the events the system has already read.
+///
+/// The buffers of events registered with [World::register_event](crate::World::register_event) are
+/// swapped automatically at the end of every update.
 pub struct Events<T> {
     read: Vec<T>,
     write: Vec<T>,
+
+    /// Every event is numbered in the order it was pushed. This is the number of the first event in the
+    /// read buffer, which the write buffer follows on from.
+    read_start: usize,
+    /// The number that the next event pushed will have
+    event_count: usize,
 }
 
 impl<T> Events<T> {
@@ -34,19 +81,246 @@ impl<T> Events<T> {
         Self {
             read: Vec::new(),
             write: Vec::new(),
+            read_start: 0,
+            event_count: 0,
         }
     }
 
     pub fn push(&mut self, event: T) {
         self.write.push(event);
+        self.event_count += 1;
     }
 
+    /// Iterates over the events that were pushed during the previous update
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.read.iter()
     }
 
+    /// Iterates over every event still held, in either buffer, numbered from `start` onwards. Events that
+    /// have already been dropped are skipped.
+    fn iter_from(&self, start: usize) -> impl Iterator<Item = &T> {
+        let write_start = self.read_start + self.read.len();
+        let read_skip = start.saturating_sub(self.read_start).min(self.read.len());
+        let write_skip = start.saturating_sub(write_start).min(self.write.len());
+
+        self.read[read_skip..]
+            .iter()
+            .chain(self.write[write_skip..].iter())
+    }
+
+    /// Drops the events that were readable, and makes those that were pushed since the last call readable
     pub fn clear(&mut self) {
+        self.read_start += self.read.len();
         self.read.clear();
         std::mem::swap(&mut self.read, &mut self.write);
     }
 }
+
+/// A system parameter that reads events of a type. Each system keeps its own cursor, so it is given
+/// every event exactly once, as soon as it runs after the event was pushed, however many times it runs
+/// per update.
+///
+/// Events are only held for two updates, so a system that does not run in that time will miss them.
+pub struct EventReader<'w, 's, T: Send + Sync + 'static> {
+    events: &'w Events<T>,
+    cursor: &'s mut usize,
+}
+
+impl<'w, 's, T: Send + Sync + 'static> EventReader<'w, 's, T> {
+    /// Creates a reader that starts from the event numbered by the cursor, and advances it as events are
+    /// read
+    pub(crate) fn new(events: &'w Events<T>, cursor: &'s mut usize) -> Self {
+        Self { events, cursor }
+    }
+
+    /// Iterates over the events that this system has not yet read, and marks them as read
+    pub fn iter(&mut self) -> impl Iterator<Item = &'w T> {
+        let start = core::mem::replace(self.cursor, self.events.event_count);
+        self.events.iter_from(start)
+    }
+
+    /// The number of events that this system has not yet read
+    pub fn len(&self) -> usize {
+        self.events.iter_from(*self.cursor).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Marks every event as read, without reading them
+    pub fn clear(&mut self) {
+        *self.cursor = self.events.event_count;
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventReader<'_, '_, T> {
+    type State = (ResourceId<Events<T>>, usize);
+    type Item<'w, 's> = EventReader<'w, 's, T>;
+
+    /// # Panics
+    /// - If the event type has not been registered
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        (world.get_resource_id::<Events<T>>(), 0)
+    }
+
+    fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+        let mut access = Access::new();
+        access.read_resource(state.0.index);
+        meta.add_access(&access);
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        let (id, cursor) = state;
+
+        // SAFETY: The ID was retrieved from the resource manager, and we do not remove resources
+        let events = unsafe { world.get_resource_unchecked(*id) };
+        EventReader::new(events, cursor)
+    }
+}
+
+/// A system parameter that pushes events of a type, to be read by [EventReader]s
+pub struct EventWriter<'w, T: Send + Sync + 'static> {
+    events: &'w mut Events<T>,
+}
+
+impl<T: Send + Sync + 'static> EventWriter<'_, T> {
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.events.push(event);
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for EventWriter<'_, T> {
+    type State = ResourceId<Events<T>>;
+    type Item<'w, 's> = EventWriter<'w, T>;
+
+    /// # Panics
+    /// - If the event type has not been registered
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        world.get_resource_id::<Events<T>>()
+    }
+
+    fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+        let mut access = Access::new();
+        access.write_resource(state.index);
+        meta.add_access(&access);
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        EventWriter {
+            // SAFETY:
+            // - The ID was retrieved from the resource manager, and we do not remove resources
+            // - Caller ensures the resource is not borrowed elsewhere
+            events: unsafe { world.get_mut_resource_unchecked(*state) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use crate::{
+        system::{
+            config::IntoSystemConfig,
+            param::Local,
+            schedule::{ScheduleBuilder, ScheduleRate},
+        },
+        World,
+    };
+
+    use super::*;
+
+    struct Ping;
+
+    #[derive(Default)]
+    struct Seen(Vec<usize>);
+
+    #[test]
+    fn events_last_one_update() {
+        let mut world = World::new();
+        world.register_event::<Ping>();
+        let seen_id = world.add_resource(Seen::default());
+
+        let reader = |events: &Events<Ping>, seen: &mut Seen| {
+            seen.0.push(events.iter().count());
+        };
+        let writer = |events: &mut Events<Ping>, mut sent: Local<bool>| {
+            if !*sent {
+                events.push(Ping);
+                *sent = true;
+            }
+        };
+
+        // The reader runs before the writer, yet still sees the event on the next update
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .add(reader.label("reader"))
+                .add(writer.after("reader"))
+                .build()
+                .unwrap(),
+        );
+
+        for _ in 0..3 {
+            world.update();
+        }
+
+        assert_eq!(world.get_resource(seen_id).unwrap().0, vec![0, 1, 0]);
+    }
+
+    #[derive(Default)]
+    struct Counts {
+        early: Vec<usize>,
+        fixed: Vec<usize>,
+    }
+
+    #[test]
+    fn readers_keep_their_own_cursor() {
+        let mut world = World::new();
+        world.register_event::<Ping>();
+        let counts_id = world.add_resource(Counts::default());
+
+        let early_reader = |mut reader: EventReader<Ping>, counts: &mut Counts| {
+            counts.early.push(reader.iter().count());
+        };
+        let writer = |mut writer: EventWriter<Ping>| {
+            writer.send_batch([Ping, Ping]);
+        };
+        let fixed_reader = |mut reader: EventReader<Ping>, counts: &mut Counts| {
+            counts.fixed.push(reader.iter().count());
+        };
+
+        world.add_schedule(ScheduleBuilder::new().add(early_reader).build().unwrap());
+        world.add_schedule(ScheduleBuilder::new().add(writer).build().unwrap());
+        world.add_named_schedule(
+            "fixed",
+            ScheduleRate::Fixed(Duration::from_millis(10)),
+            ScheduleBuilder::new().add(fixed_reader).build().unwrap(),
+        );
+
+        world.update_with_delta(Duration::from_millis(30));
+        world.update_with_delta(Duration::from_millis(30));
+
+        let counts = world.get_resource(counts_id).unwrap();
+        // The early reader picks up the events pushed after it ran on the next update
+        assert_eq!(counts.early, vec![0, 2]);
+        // Each event is only read on the first of several runs in an update
+        assert_eq!(counts.fixed, vec![2, 0, 0, 2, 0, 0]);
+    }
+}
@@ -5,17 +5,28 @@ mod event;
 mod query;
 mod resource;
 mod system;
+mod time;
 mod util;
 mod world;
 
-pub use component::Component;
+pub use component::{
+    bundle::Bundle, removed::RemovedComponents, tracking::Mut, Component, ComponentHook,
+    ComponentHooks,
+};
 pub use entity::Entity;
-pub use event::Events;
-pub use query::bundle::{ComponentBundle, ResourceBundle};
-pub use query::filter::{And, Not, Tracked};
-pub use query::{Query, QueryBuilder};
-pub use query::iter::ComponentBundleIter;
+pub use event::{EventReader, EventWriter, Events};
+pub use query::bundle::{ComponentBundle, FilterBundle, ReadOnlyComponentBundle, ResourceBundle};
+pub use query::filter::{Added, And, AnyOf, Changed, Not, Or, Tracked};
+pub use query::{Query, QueryBuilder, QueryEntityError};
+pub use query::iter::{ComponentBundleIter, ComponentBundleParIter};
 pub use resource::{Resource, ResourceId};
-pub use system::schedule::{Schedule, ScheduleBuilder};
-pub use system::{System, SystemFn};
+pub use system::schedule::{
+    ExecutorKind, Schedule, ScheduleBuilder, ScheduleError, ScheduleLabel, ScheduleRate,
+};
+pub use system::command::CommandQueue;
+pub use system::condition::{any_with_component, on_event, resource_equals, IntoCondition};
+pub use system::config::{IntoSystemConfig, SystemConfig, SystemLabel};
+pub use system::param::{Commands, Local, SystemParam, View};
+pub use system::{IntoSystem, System, SystemFn};
+pub use time::Time;
 pub use world::*;
@@ -2,25 +2,32 @@ use core::cell::UnsafeCell;
 
 use crate::{
     archetype::Archetype,
-    component::{tracking::ChangeTracking, Component, ComponentID, ComponentManager},
+    component::{
+        bundle::Bundle,
+        storage::ComponentStorage,
+        tracking::{ChangeTicks, ChangeTracking, Mut},
+        Component, ComponentID, ComponentManager,
+    },
     entity::Entity,
     resource::{Resource, ResourceId, ResourceManager},
+    system::access::Access,
+    util::all_tuples,
 };
 
-use super::filter::{And, FilterBuilder, Not, Tracked};
+use super::filter::{Added, And, AnyOf, Changed, FilterBuilder, Not, Or, Tracked};
 
 /// A ComponentBundle is a collection of one or more components that are used to
 /// query the ECS for entities that have all of the components in the bundle.
 ///
 /// Currently, [Entity] can also be included in a ComponentBundle, but this
 /// may to change in the future.
-pub trait ComponentBundle: 'static {
+pub trait ComponentBundle {
     /// The concrete component type that this parameter represents, but with a lifetime
     type Item<'a>;
     /// The collection from which an Item can be fetched
     type Storage<'a>: Copy;
     /// Identifier for the component type
-    type Id: Copy;
+    type Id: Copy + Send + Sync;
 
     /// The number of components in the bundle
     fn count() -> usize {
@@ -30,11 +37,17 @@ pub trait ComponentBundle: 'static {
     /// Returns the component type identifier for the parameter
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id;
 
-    /// Contributes the component type to the filter, for matching with archetypes
+    /// Contributes the component type to the filter, for matching with archetypes, and records whether
+    /// it is read or written
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder;
 
-    /// Retrieves the component storage for the archetype
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a>;
+    /// Retrieves the component storage for the archetype. Whether components have changed is relative to
+    /// the given ticks.
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        ticks: ChangeTicks,
+    ) -> Self::Storage<'a>;
 
     /// # Safety
     /// - The component type associated with the parameter must match the type of the Component Storage
@@ -42,6 +55,19 @@ pub trait ComponentBundle: 'static {
     unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a>;
 }
 
+/// A ComponentBundle whose items only read components, so any number of them can be fetched at once
+/// through a shared reference to the [World](crate::World)
+///
+/// # Safety
+/// - Fetching an item must not give mutable access to any component
+pub unsafe trait ReadOnlyComponentBundle: ComponentBundle {}
+
+unsafe impl ReadOnlyComponentBundle for () {}
+unsafe impl<T: Component> ReadOnlyComponentBundle for &T {}
+unsafe impl<T: Component> ReadOnlyComponentBundle for Option<&T> {}
+unsafe impl<T: Component> ReadOnlyComponentBundle for Tracked<&T> {}
+unsafe impl ReadOnlyComponentBundle for Entity {}
+
 impl ComponentBundle for () {
     type Item<'a> = ();
     type Storage<'a> = ();
@@ -55,7 +81,11 @@ impl ComponentBundle for () {
         filter
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        _ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
         ()
     }
 
@@ -64,7 +94,7 @@ impl ComponentBundle for () {
     }
 }
 
-impl<T: Component> ComponentBundle for &'static T {
+impl<T: Component> ComponentBundle for &T {
     type Item<'a> = &'a T;
     type Storage<'a> = &'a [T];
     type Id = ComponentID;
@@ -74,10 +104,14 @@ impl<T: Component> ComponentBundle for &'static T {
     }
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
-        filter.and(*id)
+        filter.and(*id).read(*id)
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        _ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
         unsafe { archetype.get_storage(*id).as_slice() }
     }
 
@@ -86,9 +120,34 @@ impl<T: Component> ComponentBundle for &'static T {
     }
 }
 
-impl<T: Component> ComponentBundle for &'static mut T {
-    type Item<'a> = &'a mut T;
-    type Storage<'a> = &'a [UnsafeCell<T>];
+/// The storage of a component that is fetched mutably, along with its tracker if it has one, and the
+/// ticks that changes are relative to
+type MutStorage<'a, T> = (&'a [UnsafeCell<T>], Option<&'a ChangeTracking>, ChangeTicks);
+
+fn prepare_mut_storage<T: Component>(
+    storage: &ComponentStorage,
+    ticks: ChangeTicks,
+) -> MutStorage<'_, T> {
+    let data = unsafe { storage.as_slice_unsafe_cell() };
+    let tracker = storage
+        .is_tracked()
+        .then(|| unsafe { storage.get_tracker() });
+    (data, tracker, ticks)
+}
+
+/// # Safety
+/// - The index must be within the bounds of the storage
+/// - The component, and its tracking info, must not be borrowed anywhere else for `'a`
+unsafe fn fetch_mut<'a, T: Component>(storage: MutStorage<'a, T>, index: usize) -> Mut<'a, T> {
+    let (data, tracker, ticks) = storage;
+    let value = &mut *data.get_unchecked(index).get();
+    let tracker = tracker.map(|tracker| (&mut *tracker.get_unsafe_cell(index).get(), tracker));
+    Mut::new(value, tracker, ticks.this_run)
+}
+
+impl<T: Component> ComponentBundle for &mut T {
+    type Item<'a> = Mut<'a, T>;
+    type Storage<'a> = MutStorage<'a, T>;
     type Id = ComponentID;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
@@ -96,19 +155,23 @@ impl<T: Component> ComponentBundle for &'static mut T {
     }
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
-        filter.and(*id)
+        filter.and(*id).write(*id)
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
-        unsafe { archetype.get_storage(*id).as_slice_unsafe_cell() }
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
+        prepare_mut_storage(unsafe { archetype.get_storage(*id) }, ticks)
     }
 
     unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
-        &mut *storage.get_unchecked(index).get()
+        fetch_mut(storage, index)
     }
 }
 
-impl<T: Component> ComponentBundle for Option<&'static T> {
+impl<T: Component> ComponentBundle for Option<&T> {
     type Item<'a> = Option<&'a T>;
     type Storage<'a> = Option<&'a [T]>;
     type Id = ComponentID;
@@ -119,10 +182,14 @@ impl<T: Component> ComponentBundle for Option<&'static T> {
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
         // This parameter is optional, so we want the archetype to match even if the component is not present
-        filter
+        filter.read(*id)
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        _ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
         if archetype.has_component(*id) {
             unsafe { Some(archetype.get_storage(*id).as_slice()) }
         } else {
@@ -135,9 +202,9 @@ impl<T: Component> ComponentBundle for Option<&'static T> {
     }
 }
 
-impl<T: Component> ComponentBundle for Option<&'static mut T> {
-    type Item<'a> = Option<&'a mut T>;
-    type Storage<'a> = Option<&'a [UnsafeCell<T>]>;
+impl<T: Component> ComponentBundle for Option<&mut T> {
+    type Item<'a> = Option<Mut<'a, T>>;
+    type Storage<'a> = Option<MutStorage<'a, T>>;
     type Id = ComponentID;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
@@ -146,25 +213,32 @@ impl<T: Component> ComponentBundle for Option<&'static mut T> {
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
         // This parameter is optional, so we want the archetype to match even if the component is not present
-        filter
+        filter.write(*id)
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
         if archetype.has_component(*id) {
-            unsafe { Some(archetype.get_storage(*id).as_slice_unsafe_cell()) }
+            Some(prepare_mut_storage(
+                unsafe { archetype.get_storage(*id) },
+                ticks,
+            ))
         } else {
             None
         }
     }
 
     unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
-        storage.map(|storage| &mut *storage.get_unchecked(index).get())
+        storage.map(|storage| fetch_mut(storage, index))
     }
 }
 
-impl<T: Component> ComponentBundle for Tracked<&'static T> {
+impl<T: Component> ComponentBundle for Tracked<&T> {
     type Item<'a> = Tracked<&'a T>;
-    type Storage<'a> = (&'a [T], &'a ChangeTracking);
+    type Storage<'a> = (&'a [T], &'a ChangeTracking, ChangeTicks);
     type Id = ComponentID;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
@@ -174,25 +248,27 @@ impl<T: Component> ComponentBundle for Tracked<&'static T> {
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
         let filter = filter.and(*id);
         let filter = filter.track(*id);
+        let filter = filter.read(*id);
         filter
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
         let storage = unsafe { archetype.get_storage(*id) };
         let data = unsafe { storage.as_slice() };
         let tracker = unsafe { storage.get_tracker() };
-        (data, tracker)
+        (data, tracker, ticks)
     }
 
     unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
-        let tracker = storage.1;
+        let (data, tracker, ticks) = storage;
         let item_info = tracker.get(index);
-        let item = storage.0.get_unchecked(index);
+        let item = data.get_unchecked(index);
 
-        // If we are reading this component a single tick after it was modified, the `modified` and `read` ticks
-        // will be equal. This does not mean it was modified in this current tick - `read` is updated **after**
-        // all systems have been executed, so it is the tick it was *last* read, hence '>='.
-        if item_info.modified >= tracker.last_read {
+        if ticks.is_newer(item_info.modified) {
             Tracked::Modified(item)
         } else {
             Tracked::Unmodified(item)
@@ -200,9 +276,9 @@ impl<T: Component> ComponentBundle for Tracked<&'static T> {
     }
 }
 
-impl<T: Component> ComponentBundle for Tracked<&'static mut T> {
-    type Item<'a> = Tracked<&'a mut T>;
-    type Storage<'a> = (&'a [UnsafeCell<T>], &'a ChangeTracking);
+impl<T: Component> ComponentBundle for Tracked<&mut T> {
+    type Item<'a> = Tracked<Mut<'a, T>>;
+    type Storage<'a> = MutStorage<'a, T>;
     type Id = ComponentID;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
@@ -212,25 +288,25 @@ impl<T: Component> ComponentBundle for Tracked<&'static mut T> {
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
         let filter = filter.and(*id);
         let filter = filter.track(*id);
+        let filter = filter.write(*id);
         filter
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
-        let storage = unsafe { archetype.get_storage(*id) };
-        let data = unsafe { storage.as_slice_unsafe_cell() };
-        let tracker = unsafe { storage.get_tracker() };
-        (data, tracker)
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        id: &Self::Id,
+        ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
+        prepare_mut_storage(unsafe { archetype.get_storage(*id) }, ticks)
     }
 
     unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
-        let tracker = storage.1;
-        let item_info = tracker.get(index);
-        let item = &mut *storage.0.get_unchecked(index).get();
+        // The storage is always tracked, as the filter enables it
+        let tracker = storage.1.unwrap_unchecked();
+        let modified = storage.2.is_newer(tracker.get(index).modified);
+        let item = fetch_mut(storage, index);
 
-        // If we are reading this component a single tick after it was modified, the `modified` and `read` ticks
-        // will be equal. This does not mean it was modified in this current tick - `read` is updated **after**
-        // all systems have been executed, so it is the tick it was *last* read, hence '>='.
-        if item_info.modified >= tracker.last_read {
+        if modified {
             Tracked::Modified(item)
         } else {
             Tracked::Unmodified(item)
@@ -257,7 +333,11 @@ impl ComponentBundle for Entity {
         filter
     }
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, _id: &Self::Id) -> Self::Storage<'a> {
+    fn prepare_storage<'a>(
+        archetype: &'a Archetype,
+        _id: &Self::Id,
+        _ticks: ChangeTicks,
+    ) -> Self::Storage<'a> {
         &archetype.entities
     }
 
@@ -266,98 +346,153 @@ impl ComponentBundle for Entity {
     }
 }
 
-impl<P1: ComponentBundle, P2: ComponentBundle> ComponentBundle for (P1, P2) {
-    type Item<'a> = (P1::Item<'a>, P2::Item<'a>);
-    type Storage<'a> = (P1::Storage<'a>, P2::Storage<'a>);
-    type Id = (P1::Id, P2::Id);
+macro_rules! impl_component_bundle_tuple {
+    ($($P:ident $i:tt),+) => {
+        impl<$($P: ComponentBundle),+> ComponentBundle for ($($P,)+) {
+            type Item<'a> = ($($P::Item<'a>,)+);
+            type Storage<'a> = ($($P::Storage<'a>,)+);
+            type Id = ($($P::Id,)+);
+
+            fn count() -> usize {
+                0 $(+ $P::count())+
+            }
+
+            fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
+                ($($P::parameter_ids(component_manager),)+)
+            }
+
+            fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
+                $(let filter = $P::build_filter(filter, &id.$i);)+
+                filter
+            }
+
+            fn prepare_storage<'a>(
+                archetype: &'a Archetype,
+                id: &Self::Id,
+                ticks: ChangeTicks,
+            ) -> Self::Storage<'a> {
+                ($($P::prepare_storage(archetype, &id.$i, ticks),)+)
+            }
+
+            unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
+                // SAFETY: Deferred to the caller
+                ($(unsafe { $P::fetch_item(storage.$i, index) },)+)
+            }
+        }
 
-    fn count() -> usize {
-        P1::count() + P2::count()
-    }
+        unsafe impl<$($P: ReadOnlyComponentBundle),+> ReadOnlyComponentBundle for ($($P,)+) {}
+    };
+}
 
-    fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
-        (
-            P1::parameter_ids(component_manager),
-            P2::parameter_ids(component_manager),
-        )
-    }
+all_tuples!(impl_component_bundle_tuple, P0 0, P1 1, P2 2, P3 3, P4 4, P5 5, P6 6, P7 7, P8 8, P9 9, P10 10, P11 11, P12 12, P13 13, P14 14, P15 15);
+
+macro_rules! impl_any_of {
+    ($($P:ident $i:tt),+) => {
+        impl<$($P),+> ComponentBundle for AnyOf<($($P,)+)>
+        where
+            $(Option<$P>: ComponentBundle<Id = ComponentID>,)+
+        {
+            type Item<'a> = ($(<Option<$P> as ComponentBundle>::Item<'a>,)+);
+            type Storage<'a> = ($(<Option<$P> as ComponentBundle>::Storage<'a>,)+);
+            type Id = ($(<Option<$P> as ComponentBundle>::Id,)+);
+
+            fn count() -> usize {
+                0 $(+ <Option<$P>>::count())+
+            }
+
+            fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
+                ($(<Option<$P>>::parameter_ids(component_manager),)+)
+            }
+
+            fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
+                // Each parameter is optional, but the archetype must have at least one of them
+                $(let filter = <Option<$P>>::build_filter(filter, &id.$i);)+
+                filter.or(&[$(id.$i),+])
+            }
+
+            fn prepare_storage<'a>(
+                archetype: &'a Archetype,
+                id: &Self::Id,
+                ticks: ChangeTicks,
+            ) -> Self::Storage<'a> {
+                ($(<Option<$P>>::prepare_storage(archetype, &id.$i, ticks),)+)
+            }
+
+            unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
+                // SAFETY: Deferred to the caller
+                ($(unsafe { <Option<$P>>::fetch_item(storage.$i, index) },)+)
+            }
+        }
 
-    fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
-        let filter = P1::build_filter(filter, &id.0);
-        let filter = P2::build_filter(filter, &id.1);
-        filter
-    }
+        unsafe impl<$($P),+> ReadOnlyComponentBundle for AnyOf<($($P,)+)>
+        where
+            $(Option<$P>: ReadOnlyComponentBundle<Id = ComponentID>,)+
+        {
+        }
+    };
+}
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
-        (
-            P1::prepare_storage(archetype, &id.0),
-            P2::prepare_storage(archetype, &id.1),
-        )
-    }
+all_tuples!(impl_any_of, P0 0, P1 1, P2 2, P3 3, P4 4, P5 5, P6 6, P7 7, P8 8, P9 9, P10 10, P11 11, P12 12, P13 13, P14 14, P15 15);
 
-    unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
-        (
-            P1::fetch_item(storage.0, index),
-            P2::fetch_item(storage.1, index),
-        )
-    }
+pub trait FilterBundle: 'static {
+    /// Identifier for the component type
+    type Id: Copy;
+
+    /// Returns the component type identifier for the parameter
+    fn parameter_ids(component_manager: &ComponentManager) -> Self::Id;
+
+    /// Contributes the component type to the filter, for matching with archetypes, and possibly with the
+    /// rows within them
+    fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder;
 }
 
-impl<P1: ComponentBundle, P2: ComponentBundle, P3: ComponentBundle> ComponentBundle
-    for (P1, P2, P3)
-{
-    type Item<'a> = (P1::Item<'a>, P2::Item<'a>, P3::Item<'a>);
-    type Storage<'a> = (P1::Storage<'a>, P2::Storage<'a>, P3::Storage<'a>);
-    type Id = (P1::Id, P2::Id, P3::Id);
+impl FilterBundle for () {
+    type Id = ();
 
-    fn count() -> usize {
-        P1::count() + P2::count() + P3::count()
+    fn parameter_ids(_component_manager: &ComponentManager) -> Self::Id {}
+
+    fn build_filter(filter: FilterBuilder, _id: &Self::Id) -> FilterBuilder {
+        filter
     }
+}
+
+impl<T: Component> FilterBundle for Not<T> {
+    type Id = ComponentID;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
-        (
-            P1::parameter_ids(component_manager),
-            P2::parameter_ids(component_manager),
-            P3::parameter_ids(component_manager),
-        )
+        component_manager.get_id::<T>()
     }
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
-        let filter = P1::build_filter(filter, &id.0);
-        let filter = P2::build_filter(filter, &id.1);
-        let filter = P3::build_filter(filter, &id.2);
-        filter
+        filter.not(*id)
     }
+}
 
-    fn prepare_storage<'a>(archetype: &'a Archetype, id: &Self::Id) -> Self::Storage<'a> {
-        (
-            P1::prepare_storage(archetype, &id.0),
-            P2::prepare_storage(archetype, &id.1),
-            P3::prepare_storage(archetype, &id.2),
-        )
+impl<T: Component> FilterBundle for And<T> {
+    type Id = ComponentID;
+
+    fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
+        component_manager.get_id::<T>()
     }
 
-    unsafe fn fetch_item<'a>(storage: Self::Storage<'a>, index: usize) -> Self::Item<'a> {
-        (
-            P1::fetch_item(storage.0, index),
-            P2::fetch_item(storage.1, index),
-            P3::fetch_item(storage.2, index),
-        )
+    fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
+        filter.and(*id)
     }
 }
 
-pub trait FilterBundle: 'static {
-    /// Identifier for the component type
-    type Id: Copy;
+impl<T: Component> FilterBundle for Added<T> {
+    type Id = ComponentID;
 
-    /// Returns the component type identifier for the parameter
-    fn parameter_ids(component_manager: &ComponentManager) -> Self::Id;
+    fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
+        component_manager.get_id::<T>()
+    }
 
-    /// Contributes the component type to the filter, for matching with archetypes
-    fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder;
+    fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
+        filter.added(*id)
+    }
 }
 
-impl<T: Component> FilterBundle for Not<T> {
+impl<T: Component> FilterBundle for Changed<T> {
     type Id = ComponentID;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
@@ -365,32 +500,54 @@ impl<T: Component> FilterBundle for Not<T> {
     }
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
-        filter.not(*id)
+        filter.changed(*id)
     }
 }
 
-impl<T: Component> FilterBundle for And<T> {
-    type Id = ComponentID;
+impl<B: Bundle> FilterBundle for Or<B> {
+    type Id = B::Id;
 
     fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
-        component_manager.get_id::<T>()
+        B::parameter_ids(component_manager)
     }
 
     fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
-        filter.and(*id)
+        filter.or(id.as_ref())
     }
 }
 
+macro_rules! impl_filter_bundle_tuple {
+    ($($F:ident $i:tt),+) => {
+        impl<$($F: FilterBundle),+> FilterBundle for ($($F,)+) {
+            type Id = ($($F::Id,)+);
+
+            fn parameter_ids(component_manager: &ComponentManager) -> Self::Id {
+                ($($F::parameter_ids(component_manager),)+)
+            }
+
+            fn build_filter(filter: FilterBuilder, id: &Self::Id) -> FilterBuilder {
+                $(let filter = $F::build_filter(filter, &id.$i);)+
+                filter
+            }
+        }
+    };
+}
+
+all_tuples!(impl_filter_bundle_tuple, F0 0, F1 1, F2 2, F3 3, F4 4, F5 5, F6 6, F7 7, F8 8, F9 9, F10 10, F11 11, F12 12, F13 13, F14 14, F15 15);
+
 /// A ResourceBundle is a collection of resources that can be fetched from a resource manager.
 pub trait ResourceBundle: 'static {
     /// The concrete type of the Resource, but with a lifetime
     type Item<'a>;
     /// Identifier of the resource
-    type Id: Copy;
+    type Id: Copy + Send + Sync;
 
     /// Returns the identifier of the resource
     fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id;
 
+    /// Records whether the resource is read or written
+    fn update_access(id: &Self::Id, access: &mut Access);
+
     /// # Safety
     /// - The generic type must be the same as the one used to register the resource
     /// - The index must be within the bounds
@@ -408,6 +565,8 @@ impl ResourceBundle for () {
         ()
     }
 
+    fn update_access(_id: &Self::Id, _access: &mut Access) {}
+
     unsafe fn fetch_item<'a>(
         _storage: &'a [Box<UnsafeCell<dyn Resource>>],
         _key: Self::Id,
@@ -424,6 +583,10 @@ impl<R: Resource> ResourceBundle for &'static R {
         resource_manager.get_id::<R>()
     }
 
+    fn update_access(id: &Self::Id, access: &mut Access) {
+        access.read_resource(id.index);
+    }
+
     unsafe fn fetch_item<'a>(
         storage: &'a [Box<UnsafeCell<dyn Resource>>],
         key: Self::Id,
@@ -448,6 +611,10 @@ impl<R: Resource> ResourceBundle for &'static mut R {
         resource_manager.get_id::<R>()
     }
 
+    fn update_access(id: &Self::Id, access: &mut Access) {
+        access.write_resource(id.index);
+    }
+
     unsafe fn fetch_item<'a>(
         storage: &'a [Box<UnsafeCell<dyn Resource>>],
         key: ResourceId<R>,
@@ -464,162 +631,29 @@ impl<R: Resource> ResourceBundle for &'static mut R {
     }
 }
 
-impl<R1: ResourceBundle, R2: ResourceBundle> ResourceBundle for (R1, R2) {
-    type Item<'a> = (R1::Item<'a>, R2::Item<'a>);
-    type Id = (R1::Id, R2::Id);
-
-    fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id {
-        (
-            R1::parameter_ids(resource_manager),
-            R2::parameter_ids(resource_manager),
-        )
-    }
-
-    unsafe fn fetch_item<'a>(
-        storage: &'a [Box<UnsafeCell<dyn Resource>>],
-        key: Self::Id,
-    ) -> Self::Item<'a> {
-        (
-            R1::fetch_item(storage, key.0),
-            R2::fetch_item(storage, key.1),
-        )
-    }
-}
-
-impl<R1: ResourceBundle, R2: ResourceBundle, R3: ResourceBundle> ResourceBundle for (R1, R2, R3) {
-    type Item<'a> = (R1::Item<'a>, R2::Item<'a>, R3::Item<'a>);
-    type Id = (R1::Id, R2::Id, R3::Id);
-
-    fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id {
-        (
-            R1::parameter_ids(resource_manager),
-            R2::parameter_ids(resource_manager),
-            R3::parameter_ids(resource_manager),
-        )
-    }
-
-    unsafe fn fetch_item<'a>(
-        storage: &'a [Box<UnsafeCell<dyn Resource>>],
-        key: Self::Id,
-    ) -> Self::Item<'a> {
-        (
-            R1::fetch_item(storage, key.0),
-            R2::fetch_item(storage, key.1),
-            R3::fetch_item(storage, key.2),
-        )
-    }
-}
-
-impl<R1: ResourceBundle, R2: ResourceBundle, R3: ResourceBundle, R4: ResourceBundle> ResourceBundle
-    for (R1, R2, R3, R4)
-{
-    type Item<'a> = (R1::Item<'a>, R2::Item<'a>, R3::Item<'a>, R4::Item<'a>);
-    type Id = (R1::Id, R2::Id, R3::Id, R4::Id);
-
-    fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id {
-        (
-            R1::parameter_ids(resource_manager),
-            R2::parameter_ids(resource_manager),
-            R3::parameter_ids(resource_manager),
-            R4::parameter_ids(resource_manager),
-        )
-    }
-
-    unsafe fn fetch_item<'a>(
-        storage: &'a [Box<UnsafeCell<dyn Resource>>],
-        key: Self::Id,
-    ) -> Self::Item<'a> {
-        (
-            R1::fetch_item(storage, key.0),
-            R2::fetch_item(storage, key.1),
-            R3::fetch_item(storage, key.2),
-            R4::fetch_item(storage, key.3),
-        )
-    }
-}
-
-impl<
-        R1: ResourceBundle,
-        R2: ResourceBundle,
-        R3: ResourceBundle,
-        R4: ResourceBundle,
-        R5: ResourceBundle,
-    > ResourceBundle for (R1, R2, R3, R4, R5)
-{
-    type Item<'a> = (
-        R1::Item<'a>,
-        R2::Item<'a>,
-        R3::Item<'a>,
-        R4::Item<'a>,
-        R5::Item<'a>,
-    );
-    type Id = (R1::Id, R2::Id, R3::Id, R4::Id, R5::Id);
-
-    fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id {
-        (
-            R1::parameter_ids(resource_manager),
-            R2::parameter_ids(resource_manager),
-            R3::parameter_ids(resource_manager),
-            R4::parameter_ids(resource_manager),
-            R5::parameter_ids(resource_manager),
-        )
-    }
-
-    unsafe fn fetch_item<'a>(
-        storage: &'a [Box<UnsafeCell<dyn Resource>>],
-        key: Self::Id,
-    ) -> Self::Item<'a> {
-        (
-            R1::fetch_item(storage, key.0),
-            R2::fetch_item(storage, key.1),
-            R3::fetch_item(storage, key.2),
-            R4::fetch_item(storage, key.3),
-            R5::fetch_item(storage, key.4),
-        )
-    }
+macro_rules! impl_resource_bundle_tuple {
+    ($($R:ident $i:tt),+) => {
+        impl<$($R: ResourceBundle),+> ResourceBundle for ($($R,)+) {
+            type Item<'a> = ($($R::Item<'a>,)+);
+            type Id = ($($R::Id,)+);
+
+            fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id {
+                ($($R::parameter_ids(resource_manager),)+)
+            }
+
+            fn update_access(id: &Self::Id, access: &mut Access) {
+                $($R::update_access(&id.$i, access);)+
+            }
+
+            unsafe fn fetch_item<'a>(
+                storage: &'a [Box<UnsafeCell<dyn Resource>>],
+                key: Self::Id,
+            ) -> Self::Item<'a> {
+                // SAFETY: Deferred to the caller
+                ($(unsafe { $R::fetch_item(storage, key.$i) },)+)
+            }
+        }
+    };
 }
 
-impl<
-        R1: ResourceBundle,
-        R2: ResourceBundle,
-        R3: ResourceBundle,
-        R4: ResourceBundle,
-        R5: ResourceBundle,
-        R6: ResourceBundle,
-    > ResourceBundle for (R1, R2, R3, R4, R5, R6)
-{
-    type Item<'a> = (
-        R1::Item<'a>,
-        R2::Item<'a>,
-        R3::Item<'a>,
-        R4::Item<'a>,
-        R5::Item<'a>,
-        R6::Item<'a>,
-    );
-    type Id = (R1::Id, R2::Id, R3::Id, R4::Id, R5::Id, R6::Id);
-
-    fn parameter_ids(resource_manager: &ResourceManager) -> Self::Id {
-        (
-            R1::parameter_ids(resource_manager),
-            R2::parameter_ids(resource_manager),
-            R3::parameter_ids(resource_manager),
-            R4::parameter_ids(resource_manager),
-            R5::parameter_ids(resource_manager),
-            R6::parameter_ids(resource_manager),
-        )
-    }
-
-    unsafe fn fetch_item<'a>(
-        storage: &'a [Box<UnsafeCell<dyn Resource>>],
-        key: Self::Id,
-    ) -> Self::Item<'a> {
-        (
-            R1::fetch_item(storage, key.0),
-            R2::fetch_item(storage, key.1),
-            R3::fetch_item(storage, key.2),
-            R4::fetch_item(storage, key.3),
-            R5::fetch_item(storage, key.4),
-            R6::fetch_item(storage, key.5),
-        )
-    }
-}
+all_tuples!(impl_resource_bundle_tuple, R0 0, R1 1, R2 2, R3 3, R4 4, R5 5, R6 6, R7 7, R8 8, R9 9, R10 10, R11 11, R12 12, R13 13, R14 14, R15 15);
@@ -1,14 +1,24 @@
+use core::marker::PhantomData;
+
 use collections::BitSet;
 
 use crate::{
     archetype::{Archetype, ArchetypeID, ArchetypeManager},
-    component::ComponentID,
+    component::{tracking::ChangeTicks, ComponentID},
+    system::access::{Access, AccessSet},
 };
 
 pub struct FilterBuilder {
     and: Vec<ComponentID>,
     not: Vec<ComponentID>,
+    or: Vec<Vec<ComponentID>>,
     track: Vec<ComponentID>,
+    added: Vec<ComponentID>,
+    changed: Vec<ComponentID>,
+    access: Access,
+    /// The components that are fetched as items, so that a component fetched mutably can't alias another
+    /// fetch of it
+    fetched: AccessSet,
 }
 
 impl FilterBuilder {
@@ -16,7 +26,12 @@ impl FilterBuilder {
         Self {
             and: Vec::new(),
             not: Vec::new(),
+            or: Vec::new(),
             track: Vec::new(),
+            added: Vec::new(),
+            changed: Vec::new(),
+            access: Access::new(),
+            fetched: AccessSet::new(),
         }
     }
 
@@ -24,7 +39,12 @@ impl FilterBuilder {
         Self {
             and: Vec::with_capacity(capacity),
             not: Vec::with_capacity(capacity),
+            or: Vec::new(),
             track: Vec::with_capacity(capacity),
+            added: Vec::new(),
+            changed: Vec::new(),
+            access: Access::new(),
+            fetched: AccessSet::new(),
         }
     }
 
@@ -38,11 +58,64 @@ impl FilterBuilder {
         self
     }
 
+    /// Only matches archetypes that have at least one of the components. Each call adds a separate
+    /// disjunction, all of which must be satisfied.
+    pub fn or(mut self, components: &[ComponentID]) -> Self {
+        self.or.push(components.to_vec());
+        self
+    }
+
     pub fn track(mut self, component: ComponentID) -> Self {
         self.track.push(component);
         self
     }
 
+    /// Only matches rows where the component was added since the system last ran. This implies
+    /// [and](Self::and), and that the component is tracked and read, though not that it is fetched.
+    pub fn added(mut self, component: ComponentID) -> Self {
+        self.added.push(component);
+        self.access.read_component(component);
+        self.and(component).track(component)
+    }
+
+    /// Only matches rows where the component was modified, or added, since the system last ran. This
+    /// implies [and](Self::and), and that the component is tracked and read, though not that it is fetched.
+    pub fn changed(mut self, component: ComponentID) -> Self {
+        self.changed.push(component);
+        self.access.read_component(component);
+        self.and(component).track(component)
+    }
+
+    /// Records that the component is fetched for reading, though this does not affect which archetypes are
+    /// matched
+    ///
+    /// # Panics
+    /// - If the component is also fetched for writing
+    pub fn read(mut self, component: ComponentID) -> Self {
+        if self.fetched.write_bitset.test(component) {
+            conflicting_fetch(component);
+        }
+
+        self.fetched.read(component);
+        self.access.read_component(component);
+        self
+    }
+
+    /// Records that the component is fetched for writing, though this does not affect which archetypes are
+    /// matched
+    ///
+    /// # Panics
+    /// - If the component is also fetched elsewhere, for reading or writing
+    pub fn write(mut self, component: ComponentID) -> Self {
+        if self.fetched.read_bitset.test(component) || self.fetched.write_bitset.test(component) {
+            conflicting_fetch(component);
+        }
+
+        self.fetched.write(component);
+        self.access.write_component(component);
+        self
+    }
+
     pub fn build(self) -> Filter {
         let mut and_bitset = BitSet::new();
         for component in self.and.iter() {
@@ -54,24 +127,60 @@ impl FilterBuilder {
             not_bitset.set(*component);
         }
 
+        let or_bitsets = self
+            .or
+            .iter()
+            .map(|components| {
+                let mut bitset = BitSet::new();
+                for component in components.iter() {
+                    bitset.set(*component);
+                }
+                bitset
+            })
+            .collect();
+
         Filter {
             and: self.and,
             not: self.not,
+            or: self.or,
             track: self.track,
+            added: self.added,
+            changed: self.changed,
+            access: self.access,
 
             and_bitset,
             not_bitset,
+            or_bitsets,
         }
     }
 }
 
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn conflicting_fetch(component: ComponentID) -> ! {
+    panic!(
+        "Component {} is fetched mutably, and more than once, by the same bundle",
+        component
+    );
+}
+
 pub struct Filter {
     pub and: Vec<ComponentID>,
     pub not: Vec<ComponentID>,
+    /// Disjunctions of components, where an archetype must have at least one component from each
+    pub or: Vec<Vec<ComponentID>>,
     pub track: Vec<ComponentID>,
+    /// Components that must have been added since the system last ran, for a row to match
+    pub added: Vec<ComponentID>,
+    /// Components that must have been modified since the system last ran, for a row to match
+    pub changed: Vec<ComponentID>,
+    /// The components that are read or written by whatever uses this filter
+    pub access: Access,
 
     pub and_bitset: BitSet,
     pub not_bitset: BitSet,
+    pub or_bitsets: Vec<BitSet>,
 }
 
 impl Filter {
@@ -79,7 +188,11 @@ impl Filter {
         let matches = archetype.component_id_bitset.contains(&self.and_bitset)
             && archetype
                 .component_id_bitset
-                .contains_none(&self.not_bitset);
+                .contains_none(&self.not_bitset)
+            && self
+                .or_bitsets
+                .iter()
+                .all(|any| !archetype.component_id_bitset.contains_none(any));
 
         if matches {
             // Enable tracking for components that have opted in (via Tracked<T> parameter)
@@ -91,6 +204,54 @@ impl Filter {
         matches
     }
 
+    /// Returns true if rows are filtered by when their components changed, in addition to the archetype
+    /// matching
+    pub fn filters_changes(&self) -> bool {
+        !self.added.is_empty() || !self.changed.is_empty()
+    }
+
+    /// Returns false if no row of the archetype can match the change filters, as none of the filtered
+    /// storages have been written to since the system last ran
+    ///
+    /// # Safety
+    /// - The archetype must match this filter, so that the filtered storages exist and are tracked
+    pub unsafe fn may_match_changes(&self, archetype: &Archetype, ticks: ChangeTicks) -> bool {
+        // SAFETY: Caller ensures the archetype matches, so has tracked storages for these components
+        let last_write = |comp_id: &ComponentID| unsafe {
+            archetype.get_storage(*comp_id).get_tracker().last_write()
+        };
+
+        self.added
+            .iter()
+            .chain(self.changed.iter())
+            .all(|comp_id| ticks.is_newer(last_write(comp_id)))
+    }
+
+    /// Returns true if the row matches the change filters
+    ///
+    /// # Safety
+    /// - The archetype must match this filter, so that the filtered storages exist and are tracked
+    /// - The row must be within the bounds of the archetype
+    pub unsafe fn matches_changes(
+        &self,
+        archetype: &Archetype,
+        row: usize,
+        ticks: ChangeTicks,
+    ) -> bool {
+        // SAFETY: Caller ensures the archetype matches, and the row is within bounds
+        let info = |comp_id: &ComponentID| unsafe {
+            archetype.get_storage(*comp_id).get_tracker().get(row)
+        };
+
+        self.added
+            .iter()
+            .all(|comp_id| ticks.is_newer(info(comp_id).added))
+            && self
+                .changed
+                .iter()
+                .all(|comp_id| ticks.is_newer(info(comp_id).modified))
+    }
+
     pub fn matching_archetypes(
         &self,
         archetype_manager: &mut ArchetypeManager,
@@ -138,6 +299,29 @@ pub struct And<T> {
     pub(crate) inner: T,
 }
 
+/// Only matches entities whose component of type `T` was added since the system last ran
+pub struct Added<T> {
+    _marker: PhantomData<T>,
+}
+
+/// Only matches entities whose component of type `T` was modified, or added, since the system last ran
+pub struct Changed<T> {
+    _marker: PhantomData<T>,
+}
+
 pub struct Not<T> {
     pub(crate) inner: T,
 }
+
+/// Only matches entities that have at least one of the components in the tuple `T`
+pub struct Or<T> {
+    _marker: PhantomData<T>,
+}
+
+/// Fetches each of the component parameters in the tuple `T` as an [Option], only matching entities that
+/// have at least one of them
+///
+/// For example, `AnyOf<(&A, &mut B)>` yields `(Option<&A>, Option<Mut<B>>)`.
+pub struct AnyOf<T> {
+    _marker: PhantomData<T>,
+}
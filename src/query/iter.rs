@@ -1,13 +1,28 @@
-use crate::archetype::{ArchetypeID, ArchetypeManager};
+use core::{
+    num::NonZeroUsize,
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::thread;
 
-use super::bundle::ComponentBundle;
+use crate::archetype::{Archetype, ArchetypeID, ArchetypeManager};
 
+use crate::component::tracking::ChangeTicks;
+
+use super::{bundle::ComponentBundle, filter::Filter};
+
+/// Iterates over the items of every archetype matching a query.
+///
+/// If the query filters by when components changed, whole archetypes are skipped where none of the
+/// filtered components have been written to since the system last ran, and otherwise each row is checked.
 pub struct ComponentBundleIter<'w, 'q, C: ComponentBundle> {
     parameter_ids: &'q C::Id,
     archetype_manager: &'w ArchetypeManager,
     archetype_id_iter: core::slice::Iter<'q, ArchetypeID>,
+    filter: &'q Filter,
+    ticks: ChangeTicks,
 
-    chunk_iter: Option<ComponentChunkIter<'w, C>>,
+    chunk_iter: Option<ComponentChunkIter<'w, 'q, C>>,
 }
 
 impl<'w, 'q, C: ComponentBundle> ComponentBundleIter<'w, 'q, C> {
@@ -15,28 +30,42 @@ impl<'w, 'q, C: ComponentBundle> ComponentBundleIter<'w, 'q, C> {
         archetype_manager: &'w ArchetypeManager,
         parameter_ids: &'q C::Id,
         archetype_ids: &'q [ArchetypeID],
+        filter: &'q Filter,
+        ticks: ChangeTicks,
     ) -> Self {
         Self {
             archetype_manager,
             parameter_ids,
             archetype_id_iter: archetype_ids.iter(),
+            filter,
+            ticks,
 
             chunk_iter: None,
         }
     }
 
-    fn next_chunk(&mut self) -> Option<ComponentChunkIter<'w, C>> {
-        let archetype_id = self.archetype_id_iter.next()?;
+    fn next_chunk(&mut self) -> Option<ComponentChunkIter<'w, 'q, C>> {
+        loop {
+            let archetype_id = self.archetype_id_iter.next()?;
 
-        // SAFETY:
-        // - The archetype ID will definitely be valid as the iter was built using IDs from the
-        //   archetype manager itself.
-        let archetype = unsafe { self.archetype_manager.get(*archetype_id) };
+            // SAFETY:
+            // - The archetype ID will definitely be valid as the iter was built using IDs from the
+            //   archetype manager itself.
+            let archetype = unsafe { self.archetype_manager.get(*archetype_id) };
+
+            let rows = RowFilter::new(archetype, self.filter, self.ticks);
+            if rows.as_ref().is_some_and(|rows| !rows.may_match()) {
+                continue;
+            }
 
-        Some(ComponentChunkIter::new(
-            C::prepare_storage(archetype, self.parameter_ids),
-            archetype.entities.len(),
-        ))
+            return Some(
+                ComponentChunkIter::new(
+                    C::prepare_storage(archetype, self.parameter_ids, self.ticks),
+                    archetype.entities.len(),
+                )
+                .filtered(rows),
+            );
+        }
     }
 }
 
@@ -68,42 +97,236 @@ impl<'w, 'q, C: ComponentBundle> Iterator for ComponentBundleIter<'w, 'q, C> {
             .sum::<usize>()
             + self.chunk_iter.as_ref().map(|iter| iter.len - iter.index).unwrap_or(0);
 
-        (remaining, Some(remaining))
+        if self.filter.filters_changes() {
+            (0, Some(remaining))
+        } else {
+            (remaining, Some(remaining))
+        }
     }
 }
 
-pub struct ComponentChunkIter<'w, C: ComponentBundle> {
+/// Checks each row of an archetype against the change filters of a query
+#[derive(Clone, Copy)]
+struct RowFilter<'w, 'q> {
+    archetype: &'w Archetype,
+    filter: &'q Filter,
+    ticks: ChangeTicks,
+}
+
+impl<'w, 'q> RowFilter<'w, 'q> {
+    /// Returns `None` if the filter does not filter changes, so every row matches
+    ///
+    /// The archetype must match the filter.
+    fn new(archetype: &'w Archetype, filter: &'q Filter, ticks: ChangeTicks) -> Option<Self> {
+        filter.filters_changes().then_some(Self {
+            archetype,
+            filter,
+            ticks,
+        })
+    }
+
+    /// Returns false if no row of the archetype can match
+    fn may_match(&self) -> bool {
+        // SAFETY: Archetypes are only iterated over if they matched the filter
+        unsafe { self.filter.may_match_changes(self.archetype, self.ticks) }
+    }
+
+    /// # Safety
+    /// - The row must be within the bounds of the archetype
+    #[inline(always)]
+    unsafe fn matches(&self, row: usize) -> bool {
+        // SAFETY: The archetype matched the filter, and the caller ensures the row is within bounds
+        unsafe { self.filter.matches_changes(self.archetype, row, self.ticks) }
+    }
+}
+
+pub struct ComponentChunkIter<'w, 'q, C: ComponentBundle> {
     storages: C::Storage<'w>,
     index: usize,
     len: usize,
+    rows: Option<RowFilter<'w, 'q>>,
 }
 
-impl<'w, C: ComponentBundle> ComponentChunkIter<'w, C> {
+impl<'w, 'q, C: ComponentBundle> ComponentChunkIter<'w, 'q, C> {
     pub fn new(storages: C::Storage<'w>, len: usize) -> Self {
         Self {
             storages,
             index: 0,
             len,
+            rows: None,
         }
     }
+
+    /// Creates an iterator over only the rows within the range
+    pub fn from_range(storages: C::Storage<'w>, rows: Range<usize>) -> Self {
+        Self {
+            storages,
+            index: rows.start,
+            len: rows.end,
+            rows: None,
+        }
+    }
+
+    /// Skips the rows that do not match the filter
+    fn filtered(mut self, rows: Option<RowFilter<'w, 'q>>) -> Self {
+        self.rows = rows;
+        self
+    }
 }
 
-impl<'w, C: ComponentBundle> Iterator for ComponentChunkIter<'w, C> {
+impl<'w, 'q, C: ComponentBundle> Iterator for ComponentChunkIter<'w, 'q, C> {
     type Item = C::Item<'w>;
 
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index == self.len {
-            return None;
-        }
+        loop {
+            if self.index == self.len {
+                return None;
+            }
+
+            let index = self.index;
+            self.index += 1;
 
-        let item = unsafe { C::fetch_item(self.storages, self.index) };
-        self.index += 1;
+            // SAFETY: The index is within the bounds of the archetype
+            if let Some(rows) = self.rows.as_ref() {
+                if !unsafe { rows.matches(index) } {
+                    continue;
+                }
+            }
 
-        Some(item)
+            return Some(unsafe { C::fetch_item(self.storages, index) });
+        }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.len - self.index, Some(self.len - self.index))
+        let remaining = self.len - self.index;
+
+        if self.rows.is_some() {
+            (0, Some(remaining))
+        } else {
+            (remaining, Some(remaining))
+        }
+    }
+}
+
+/// The default for [ComponentBundleParIter::min_batch_size]
+pub const DEFAULT_MIN_BATCH_SIZE: usize = 64;
+
+/// Iterates over the same items as a [ComponentBundleIter], but splits them into batches that are
+/// handled by separate threads.
+///
+/// # Implementation
+/// A batch is a range of rows within a single archetype, so large archetypes can be split across several
+/// batches, and several small archetypes will be handled by the same thread. Batches are sized so that
+/// the items are spread evenly across the available threads, but are never smaller than the minimum batch
+/// size, unless the archetype itself is smaller.
+///
+/// Each thread takes the next batch that has not been taken yet, until none remain, so a thread that
+/// finishes early will not sit idle while others still have work.
+pub struct ComponentBundleParIter<'w, 'q, C: ComponentBundle> {
+    parameter_ids: &'q C::Id,
+    archetype_manager: &'w ArchetypeManager,
+    archetype_ids: &'q [ArchetypeID],
+    filter: &'q Filter,
+    ticks: ChangeTicks,
+    min_batch_size: usize,
+}
+
+impl<'w, 'q, C: ComponentBundle> ComponentBundleParIter<'w, 'q, C> {
+    pub fn new(
+        archetype_manager: &'w ArchetypeManager,
+        parameter_ids: &'q C::Id,
+        archetype_ids: &'q [ArchetypeID],
+        filter: &'q Filter,
+        ticks: ChangeTicks,
+    ) -> Self {
+        Self {
+            parameter_ids,
+            archetype_manager,
+            archetype_ids,
+            filter,
+            ticks,
+            min_batch_size: DEFAULT_MIN_BATCH_SIZE,
+        }
+    }
+
+    /// Sets the minimum number of items in a batch. It is not worth handing very small batches to other
+    /// threads, so this should be larger for cheaper work.
+    pub fn min_batch_size(mut self, size: usize) -> Self {
+        self.min_batch_size = size.max(1);
+        self
+    }
+
+    /// Calls the function for every item, possibly on another thread
+    pub fn for_each<F>(self, func: F)
+    where
+        F: Fn(C::Item<'w>) + Send + Sync,
+    {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let batches = self.batches(threads);
+
+        let run_batch = |(archetype_id, rows): &(ArchetypeID, Range<usize>)| {
+            // SAFETY:
+            // - The archetype ID will definitely be valid as the iter was built using IDs from the
+            //   archetype manager itself.
+            let archetype = unsafe { self.archetype_manager.get(*archetype_id) };
+            let storages = C::prepare_storage(archetype, self.parameter_ids, self.ticks);
+
+            // Batches do not overlap, so no item will be fetched more than once
+            ComponentChunkIter::<C>::from_range(storages, rows.clone())
+                .filtered(RowFilter::new(archetype, self.filter, self.ticks))
+                .for_each(&func);
+        };
+
+        if threads == 1 || batches.len() <= 1 {
+            batches.iter().for_each(run_batch);
+            return;
+        }
+
+        let next_batch = AtomicUsize::new(0);
+        let worker = || {
+            while let Some(batch) = batches.get(next_batch.fetch_add(1, Ordering::Relaxed)) {
+                run_batch(batch);
+            }
+        };
+
+        thread::scope(|scope| {
+            // The calling thread is also a worker
+            for _ in 1..threads.min(batches.len()) {
+                scope.spawn(worker);
+            }
+
+            worker();
+        });
+    }
+
+    fn batches(&self, threads: usize) -> Vec<(ArchetypeID, Range<usize>)> {
+        // SAFETY: See `run_batch`
+        let archetype_len =
+            |id: &ArchetypeID| unsafe { self.archetype_manager.get(*id).entities.len() };
+
+        let total = self.archetype_ids.iter().map(archetype_len).sum::<usize>();
+        let batch_size = total.div_ceil(threads).max(self.min_batch_size);
+
+        let mut batches = Vec::new();
+        for archetype_id in self.archetype_ids.iter() {
+            // SAFETY: See `run_batch`
+            let archetype = unsafe { self.archetype_manager.get(*archetype_id) };
+            let rows = RowFilter::new(archetype, self.filter, self.ticks);
+            if rows.is_some_and(|rows| !rows.may_match()) {
+                continue;
+            }
+
+            let len = archetype_len(archetype_id);
+            let mut start = 0;
+
+            while start < len {
+                let end = (start + batch_size).min(len);
+                batches.push((*archetype_id, start..end));
+                start = end;
+            }
+        }
+
+        batches
     }
 }
@@ -1,17 +1,21 @@
-use core::marker::PhantomData;
+use core::{fmt, marker::PhantomData};
 
 use crate::{
     archetype::{ArchetypeID, ArchetypeManager},
-    component::ComponentManager,
+    component::{
+        tracking::{oldest_tick, ChangeTicks},
+        ComponentManager,
+    },
+    entity::Entity,
     resource::ResourceManager,
-    system::{System, SystemFn},
+    system::{command::CommandQueue, QueryFn, QueryFnMarker, System},
     World,
 };
 
 use super::{
-    bundle::{ComponentBundle, FilterBundle, ResourceBundle},
+    bundle::{ComponentBundle, FilterBundle, ReadOnlyComponentBundle, ResourceBundle},
     filter::{Filter, FilterBuilder},
-    iter::ComponentBundleIter,
+    iter::{ComponentBundleIter, ComponentBundleParIter},
 };
 
 pub struct QueryBuilder<'w, T> {
@@ -98,6 +102,31 @@ impl<'w, C: ComponentBundle, R: ResourceBundle> QueryBuilder<'w, (C, R)> {
     }
 }
 
+/// The reason that a [Query] could not fetch the items of an entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryEntityError {
+    /// The entity has been deleted
+    Dead,
+    /// The entity is alive, but it does not have the components that the query fetches, or it does not
+    /// pass the query's filters. This is also the case if the entity is in an archetype that was created
+    /// since the query was last [synced](Query::sync).
+    NotMatching,
+    /// The entity was requested more than once, so its items would alias each other
+    Aliased(Entity),
+}
+
+impl fmt::Display for QueryEntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dead => write!(f, "entity is not alive"),
+            Self::NotMatching => write!(f, "entity does not match the query"),
+            Self::Aliased(entity) => write!(f, "entity {:?} was requested more than once", entity),
+        }
+    }
+}
+
+impl std::error::Error for QueryEntityError {}
+
 /// A Query defines a set of components and resources that a system will operate on.
 ///
 /// # Implementation
@@ -105,11 +134,17 @@ impl<'w, C: ComponentBundle, R: ResourceBundle> QueryBuilder<'w, (C, R)> {
 /// relevant component bundles from those archetypes. It also provides a method to sync
 /// the query with the world, updating the archetype IDs, to account for any new archetypes
 /// that have been created since the last sync.
+///
+/// Archetypes are never deleted and their IDs are sequential, so only the archetypes with an ID
+/// greater than any seen at the last sync need to be checked.
 pub struct Query<C: ComponentBundle, R: ResourceBundle> {
     pub(crate) comp_param_ids: C::Id,
     pub(crate) res_param_ids: R::Id,
     pub(crate) archetype_ids: Vec<ArchetypeID>,
     pub(crate) filter: Filter,
+
+    /// The number of archetypes that had been checked against the filter, as of the last sync
+    pub(crate) archetypes_seen: usize,
 }
 
 impl<'w, C: ComponentBundle, R: ResourceBundle> Query<C, R> {
@@ -123,64 +158,282 @@ impl<'w, C: ComponentBundle, R: ResourceBundle> Query<C, R> {
         let res_param_ids = R::parameter_ids(resource_manager);
         let filter = C::build_filter(filter_builder, &comp_param_ids).build();
         let archetype_ids = filter.matching_archetypes(archetype_manager);
+        let archetypes_seen = archetype_manager.archetype_table.len();
 
         Self {
             comp_param_ids,
             res_param_ids,
             archetype_ids,
             filter,
+            archetypes_seen,
         }
     }
 
-    pub fn into_system(self, system_fn: SystemFn<C, R>) -> System<C, R> {
+    pub fn into_system<F>(self, system_fn: F) -> System<QueryFnMarker, QueryFn<C, R, F>>
+    where
+        C: 'static,
+        F: FnMut(ComponentBundleIter<'_, '_, C>, R::Item<'_>, &mut CommandQueue)
+            + Send
+            + Sync
+            + 'static,
+    {
         System::new(self, system_fn)
     }
 
-    pub fn iter(&self, world: &'w World) -> ComponentBundleIter<'w, '_, C> {
+    /// Returns an iterator over the items. If the query filters by when components changed, every component
+    /// counts as changed.
+    pub fn iter(&self, world: &'w World) -> ComponentBundleIter<'w, '_, C>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        self.iter_since(world, oldest_tick(world.tick))
+    }
+
+    /// Returns an iterator over the items, where any change filters only match components that changed
+    /// after the given world tick
+    pub fn iter_since(&self, world: &'w World, last_run: u32) -> ComponentBundleIter<'w, '_, C>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        // SAFETY: The items only read components
+        unsafe { self.iter_unchecked(world, last_run) }
+    }
+
+    /// Returns an iterator over the items, borrowing the world exclusively so that the items can be
+    /// modified without aliasing any others
+    pub fn iter_mut(&self, world: &'w mut World) -> ComponentBundleIter<'w, '_, C> {
+        // SAFETY: The world is borrowed exclusively for as long as the items live
+        unsafe { self.iter_unchecked(world, oldest_tick(world.tick)) }
+    }
+
+    /// # Safety
+    /// - Any component that the items write to must not be accessed elsewhere while they live
+    pub(crate) unsafe fn iter_unchecked(
+        &self,
+        world: &'w World,
+        last_run: u32,
+    ) -> ComponentBundleIter<'w, '_, C> {
         ComponentBundleIter::<'w, '_, C>::new(
             &world.archetype_manager,
             &self.comp_param_ids,
             &self.archetype_ids,
+            &self.filter,
+            ChangeTicks::new(last_run, world.tick),
         )
     }
 
+    /// Returns an iterator that handles batches of items on separate threads
+    pub fn par_iter(&self, world: &'w World) -> ComponentBundleParIter<'w, '_, C>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        self.par_iter_since(world, oldest_tick(world.tick))
+    }
+
+    /// Returns an iterator that handles batches of items on separate threads, where any change filters
+    /// only match components that changed after the given world tick
+    pub fn par_iter_since(
+        &self,
+        world: &'w World,
+        last_run: u32,
+    ) -> ComponentBundleParIter<'w, '_, C>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        // SAFETY: The items only read components
+        unsafe { self.par_iter_unchecked(world, last_run) }
+    }
+
+    /// Returns an iterator that handles batches of items on separate threads, borrowing the world
+    /// exclusively so that the items can be modified without aliasing any others
+    pub fn par_iter_mut(&self, world: &'w mut World) -> ComponentBundleParIter<'w, '_, C> {
+        // SAFETY: The world is borrowed exclusively for as long as the items live
+        unsafe { self.par_iter_unchecked(world, oldest_tick(world.tick)) }
+    }
+
+    /// # Safety
+    /// - Any component that the items write to must not be accessed elsewhere while they live
+    pub(crate) unsafe fn par_iter_unchecked(
+        &self,
+        world: &'w World,
+        last_run: u32,
+    ) -> ComponentBundleParIter<'w, '_, C> {
+        ComponentBundleParIter::<'w, '_, C>::new(
+            &world.archetype_manager,
+            &self.comp_param_ids,
+            &self.archetype_ids,
+            &self.filter,
+            ChangeTicks::new(last_run, world.tick),
+        )
+    }
+
+    /// Returns the items of a single entity. If the query filters by when components changed, every
+    /// component counts as changed.
+    ///
+    /// The query must have been [synced](Self::sync) since the entity's archetype was created, otherwise
+    /// [QueryEntityError::NotMatching] is returned even if the entity would match.
+    pub fn get(&self, world: &'w World, entity: Entity) -> Result<C::Item<'w>, QueryEntityError>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        self.get_since(world, entity, oldest_tick(world.tick))
+    }
+
+    /// Returns the items of a single entity, where any change filters only match components that changed
+    /// after the given world tick
+    ///
+    /// As with [Self::get], the query must have been [synced](Self::sync) since the entity's archetype was
+    /// created.
+    pub fn get_since(
+        &self,
+        world: &'w World,
+        entity: Entity,
+        last_run: u32,
+    ) -> Result<C::Item<'w>, QueryEntityError>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        // SAFETY: The items only read components
+        unsafe { self.get_unchecked(world, entity, last_run) }
+    }
+
+    /// Returns the items of a single entity, borrowing the world exclusively so that the items can be
+    /// modified without aliasing any others. The query is synced first, so the entity is found even if its
+    /// archetype is new.
+    pub fn get_mut(
+        &mut self,
+        world: &'w mut World,
+        entity: Entity,
+    ) -> Result<C::Item<'w>, QueryEntityError> {
+        self.sync(world);
+
+        // SAFETY: The world is borrowed exclusively for as long as the items live
+        unsafe { self.get_unchecked(world, entity, oldest_tick(world.tick)) }
+    }
+
+    /// # Safety
+    /// - Any component that the items write to must not be accessed elsewhere while they live
+    pub(crate) unsafe fn get_unchecked(
+        &self,
+        world: &'w World,
+        entity: Entity,
+        last_run: u32,
+    ) -> Result<C::Item<'w>, QueryEntityError> {
+        let ticks = ChangeTicks::new(last_run, world.tick);
+        let (archetype_id, row) = self.locate(world, entity, ticks)?;
+
+        // SAFETY: The entity was just found to match the query, and the caller ensures the items don't alias
+        Ok(unsafe { self.fetch_row(world, archetype_id, row, ticks) })
+    }
+
+    /// Returns the items of several entities at once, borrowing the world exclusively so that the items can
+    /// be modified without aliasing any others. As with [Self::get_mut], the query is synced first.
+    ///
+    /// The entities must be distinct, otherwise [QueryEntityError::Aliased] is returned.
+    pub fn get_many_mut<const N: usize>(
+        &mut self,
+        world: &'w mut World,
+        entities: [Entity; N],
+    ) -> Result<[C::Item<'w>; N], QueryEntityError> {
+        for (i, entity) in entities.iter().enumerate() {
+            if entities[i + 1..].contains(entity) {
+                return Err(QueryEntityError::Aliased(*entity));
+            }
+        }
+
+        self.sync(world);
+
+        let world: &'w World = world;
+        let ticks = ChangeTicks::new(oldest_tick(world.tick), world.tick);
+
+        let mut locations = [(0, 0); N];
+        for (location, entity) in locations.iter_mut().zip(entities) {
+            *location = self.locate(world, entity, ticks)?;
+        }
+
+        // SAFETY:
+        // - Each entity was just found to match the query
+        // - The entities are distinct, so their rows are too, and none of the items alias each other
+        Ok(locations
+            .map(|(archetype_id, row)| unsafe { self.fetch_row(world, archetype_id, row, ticks) }))
+    }
+
+    /// Returns the archetype and row of the entity, if it matches the query
+    fn locate(
+        &self,
+        world: &World,
+        entity: Entity,
+        ticks: ChangeTicks,
+    ) -> Result<(ArchetypeID, usize), QueryEntityError> {
+        if !world.entity_manager.alive(entity) {
+            return Err(QueryEntityError::Dead);
+        }
+
+        // SAFETY: We just checked that the entity is alive
+        let record = unsafe { world.entity_manager.get_record(entity) };
+
+        // Matching archetypes are only ever appended, in order of their ID
+        if self
+            .archetype_ids
+            .binary_search(&record.archetype_id)
+            .is_err()
+        {
+            return Err(QueryEntityError::NotMatching);
+        }
+
+        // SAFETY: The archetype of an alive entity is valid
+        let archetype = unsafe { world.archetype_manager.get(record.archetype_id) };
+        let row = record.archetype_row;
+
+        // SAFETY: The archetype matches the filter, and the row of an alive entity is within its bounds
+        if self.filter.filters_changes()
+            && !unsafe { self.filter.matches_changes(archetype, row, ticks) }
+        {
+            return Err(QueryEntityError::NotMatching);
+        }
+
+        Ok((record.archetype_id, row))
+    }
+
+    /// # Safety
+    /// - The row of the archetype must have been found by [Self::locate]
+    /// - Any items that are mutable must not alias those of another fetch
+    unsafe fn fetch_row(
+        &self,
+        world: &'w World,
+        archetype_id: ArchetypeID,
+        row: usize,
+        ticks: ChangeTicks,
+    ) -> C::Item<'w> {
+        // SAFETY: Caller ensures the archetype matches the query
+        let archetype = unsafe { world.archetype_manager.get(archetype_id) };
+        let storage = C::prepare_storage(archetype, &self.comp_param_ids, ticks);
+
+        // SAFETY: The archetype matches the query, so the storages are of the fetched component types, and
+        //         the row is within their bounds
+        unsafe { C::fetch_item(storage, row) }
+    }
+
     pub fn sync(&mut self, world: &mut World) {
         self.update_archetype_ids(&mut world.archetype_manager);
-        self.update_storage_trackers(&mut world.archetype_manager, world.tick);
     }
 
     fn update_archetype_ids(&mut self, archetype_manager: &mut ArchetypeManager) {
-        for &arche_id in archetype_manager.new_archetypes_queue.iter() {
-            let mut archetype = unsafe {
-                archetype_manager
-                    .archetype_table
-                    .get_unchecked_mut(arche_id)
-            };
-            if self.filter.matches_archetype(&mut archetype) {
+        for archetype in archetype_manager.archetype_table[self.archetypes_seen..].iter_mut() {
+            if self.filter.matches_archetype(archetype) {
                 self.archetype_ids.push(archetype.id);
             }
         }
-    }
 
-    fn update_storage_trackers(&mut self, archetype_manager: &mut ArchetypeManager, tick: u32) {
-        // this updates the last_read of all tracked components
-        for &arche_id in self.archetype_ids.iter() {
-            for &comp_id in self.filter.track.iter() {
-                unsafe {
-                    archetype_manager
-                        .get_mut(arche_id)
-                        .get_mut_storage(comp_id)
-                        .get_mut_tracker()
-                        .last_read = tick
-                };
-            }
-        }
+        self.archetypes_seen = archetype_manager.archetype_table.len();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{entity::Entity, And, World};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{entity::Entity, And, AnyOf, Changed, Not, Or, QueryEntityError, World};
 
     struct Speed {
         v: usize,
@@ -233,7 +486,7 @@ mod tests {
 
         let now = std::time::Instant::now();
 
-        for (s, h) in query.iter(&mut world) {
+        for (mut s, mut h) in query.iter_mut(&mut world) {
             s.v += 3;
             h.v *= s.v;
         }
@@ -244,8 +497,8 @@ mod tests {
 
         let now = std::time::Instant::now();
 
-        for (entity, h) in query.iter(&mut world) {
-            // println!("{}", entity & (1 << 22) - 1);
+        for (entity, mut h) in query.iter_mut(&mut world) {
+            // println!("{:?}", entity);
             h.v *= 3;
         }
 
@@ -275,7 +528,7 @@ mod tests {
         let query = world.query::<(&mut Speed, Option<&mut Power>)>().build();
 
         let now = std::time::Instant::now();
-        for (s, h) in query.iter(&mut world) {
+        for (mut s, h) in query.iter_mut(&mut world) {
             if let Some(h) = h {
                 s.v += h.v;
             } else {
@@ -288,7 +541,7 @@ mod tests {
         let query = world.query::<(&mut Speed, &mut Health)>().build();
 
         let now = std::time::Instant::now();
-        for (s, h) in query.iter(&mut world) {
+        for (mut s, h) in query.iter_mut(&mut world) {
             s.v *= h.v;
         }
         println!("time: {:?}", now.elapsed());
@@ -324,7 +577,7 @@ mod tests {
         let now = std::time::Instant::now();
 
         let mut count = 0;
-        for (s, h) in query.iter(&mut world) {
+        for (s, h) in query.iter_mut(&mut world) {
             // println!("s: {}, h: {}, p: {}", s.v, h.v, p.v);
             count += 1;
         }
@@ -333,4 +586,221 @@ mod tests {
 
         println!("time: {:?}", now.elapsed());
     }
+
+    #[test]
+    fn par_iter() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+
+        for i in 0..1_000 {
+            let player = world.create_entity();
+            world.add_component(player, Speed { v: i });
+
+            if i % 3 == 0 {
+                world.add_component(player, Health { v: i });
+            }
+        }
+
+        let query = world.query::<&mut Speed>().build();
+        assert_eq!(query.archetype_ids.len(), 2);
+
+        query
+            .par_iter_mut(&mut world)
+            .min_batch_size(16)
+            .for_each(|mut s| s.v *= 2);
+
+        let query = world.query::<&Speed>().build();
+        let sum = AtomicUsize::new(0);
+        let count = AtomicUsize::new(0);
+        query.par_iter(&world).min_batch_size(1).for_each(|s| {
+            sum.fetch_add(s.v, Ordering::Relaxed);
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert_eq!(count.into_inner(), 1_000);
+        assert_eq!(sum.into_inner(), (0..1_000).sum::<usize>() * 2);
+    }
+
+    #[test]
+    fn or_and_any_of() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        world.register_component::<Power>();
+        world.register_component::<Super>();
+
+        for i in 0..10 {
+            world.spawn((Speed { v: i },));
+            world.spawn((Health { v: i },));
+            world.spawn((Speed { v: i }, Health { v: i }));
+            world.spawn((Power { v: i },));
+            world.spawn((Speed { v: i }, Power { v: i }, Super));
+        }
+
+        let query = world
+            .query::<Entity>()
+            .filter::<Or<(Speed, Health)>>()
+            .build();
+        assert_eq!(query.iter(&world).count(), 40);
+
+        // Each disjunction must be satisfied
+        let query = world
+            .query::<Entity>()
+            .filter::<(Or<(Speed, Health)>, Or<(Power, Super)>, Not<Health>)>()
+            .build();
+        assert_eq!(query.iter(&world).count(), 10);
+
+        let query = world.query::<AnyOf<(&mut Speed, &Health)>>().build();
+        assert_eq!(query.archetype_ids.len(), 4);
+
+        let (mut speeds, mut healths, mut both) = (0, 0, 0);
+        for (speed, health) in query.iter_mut(&mut world) {
+            match (speed, health) {
+                (Some(mut s), Some(h)) => {
+                    s.v += h.v;
+                    both += 1;
+                }
+                (Some(_), None) => speeds += 1,
+                (None, Some(_)) => healths += 1,
+                (None, None) => unreachable!(),
+            }
+        }
+        assert_eq!((speeds, healths, both), (20, 10, 10));
+
+        let query = world.query::<(&Speed, &Health)>().build();
+        let sum: usize = query.iter(&world).map(|(s, _)| s.v).sum();
+        assert_eq!(sum, (0..10).sum::<usize>() * 2);
+    }
+
+    #[test]
+    fn get_entity() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        world.register_component::<Super>();
+
+        let target = world.spawn((Speed { v: 1 }, Health { v: 2 }));
+        let filtered = world.spawn((Speed { v: 3 }, Health { v: 4 }, Super));
+        let partial = world.spawn((Speed { v: 5 },));
+        let dead = world.spawn((Speed { v: 7 }, Health { v: 8 }));
+        world.delete_entity(dead);
+
+        let query = world
+            .query::<(&Speed, &Health)>()
+            .filter::<Not<Super>>()
+            .build();
+
+        let (speed, health) = query.get(&world, target).unwrap();
+        assert_eq!((speed.v, health.v), (1, 2));
+
+        let mut query_mut = world.query::<(&mut Speed, &Health)>().build();
+        let (mut speed, health) = query_mut.get_mut(&mut world, target).unwrap();
+        speed.v += health.v;
+        assert_eq!(world.get_component::<Speed>(target).unwrap().v, 3);
+
+        assert_eq!(
+            query.get(&world, filtered).err(),
+            Some(QueryEntityError::NotMatching)
+        );
+        assert_eq!(
+            query.get(&world, partial).err(),
+            Some(QueryEntityError::NotMatching)
+        );
+        assert_eq!(query.get(&world, dead).err(), Some(QueryEntityError::Dead));
+    }
+
+    #[test]
+    fn get_unsynced_archetype() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        world.register_component::<Power>();
+
+        let mut query = world.query::<&Speed>().build();
+        let mut query_mut = world.query::<(&mut Speed, &Health)>().build();
+        let entity = world.spawn((Speed { v: 1 }, Health { v: 2 }, Power { v: 3 }));
+
+        // The entity's archetype was created after the query, so it is not seen until the query is synced
+        assert_eq!(
+            query.get(&world, entity).err(),
+            Some(QueryEntityError::NotMatching)
+        );
+        query.sync(&mut world);
+        assert_eq!(query.get(&world, entity).unwrap().v, 1);
+
+        // Whereas fetching mutably syncs the query first
+        let (mut speed, health) = query_mut.get_mut(&mut world, entity).unwrap();
+        speed.v += health.v;
+        assert_eq!(query.get(&world, entity).unwrap().v, 3);
+    }
+
+    #[test]
+    fn get_many_entities() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+
+        let a = world.spawn((Speed { v: 1 },));
+        let b = world.spawn((Speed { v: 2 }, Health { v: 0 }));
+        let c = world.spawn((Health { v: 0 },));
+
+        let mut query = world.query::<&mut Speed>().build();
+
+        let [mut first, mut second] = query.get_many_mut(&mut world, [a, b]).unwrap();
+        core::mem::swap(&mut first.v, &mut second.v);
+        assert_eq!(world.get_component::<Speed>(a).unwrap().v, 2);
+        assert_eq!(world.get_component::<Speed>(b).unwrap().v, 1);
+
+        assert_eq!(
+            query.get_many_mut(&mut world, [a, c]).err(),
+            Some(QueryEntityError::NotMatching)
+        );
+
+        world.delete_entity(b);
+        assert_eq!(
+            query.get_many_mut(&mut world, [a, b]).err(),
+            Some(QueryEntityError::Dead)
+        );
+    }
+
+    #[test]
+    fn get_many_duplicate_entities() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        let a = world.spawn((Speed { v: 1 },));
+        let b = world.spawn((Speed { v: 2 },));
+
+        let mut query = world.query::<&mut Speed>().build();
+        assert_eq!(
+            query.get_many_mut(&mut world, [a, b, a]).err(),
+            Some(QueryEntityError::Aliased(a))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn conflicting_fetches() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+
+        world.query::<(&mut Speed, (Entity, &Speed))>().build();
+    }
+
+    #[test]
+    fn filters_do_not_conflict_with_fetches() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.spawn((Speed { v: 1 },));
+
+        // Reading the same component more than once can't alias, and neither can filtering by it
+        let query = world.query::<(&Speed, &Speed)>().build();
+        assert_eq!(query.iter(&world).count(), 1);
+
+        let query = world
+            .query::<&mut Speed>()
+            .filter::<Changed<Speed>>()
+            .build();
+        assert_eq!(query.iter_mut(&mut world).count(), 1);
+    }
 }
@@ -4,8 +4,9 @@ use core::{
 };
 use std::collections::HashMap;
 
-pub trait Resource: 'static {}
-impl<T: Any> Resource for T {}
+/// Resources must be [Send] and [Sync], as systems that access them may be run on other threads
+pub trait Resource: Send + Sync + 'static {}
+impl<T: Any + Send + Sync> Resource for T {}
 
 pub struct ResourceId<R: Resource> {
     pub(crate) index: usize,
@@ -48,6 +49,11 @@ pub struct ResourceManager {
     pub(crate) resources: Vec<Box<UnsafeCell<dyn Resource>>>,
 }
 
+// SAFETY: Resources are themselves Sync, and mutable access through the UnsafeCell is only given out by
+//         unsafe methods, whose callers must ensure it is not aliased. Systems record which resources
+//         they write to, so that those running in parallel never do so.
+unsafe impl Sync for ResourceManager {}
+
 impl ResourceManager {
     pub fn new() -> Self {
         Self {
@@ -0,0 +1,131 @@
+use collections::BitSet;
+
+use crate::component::ComponentID;
+
+/// A set of IDs, each of which is either read or written
+pub struct AccessSet {
+    pub reads: Vec<usize>,
+    pub writes: Vec<usize>,
+
+    pub read_bitset: BitSet,
+    pub write_bitset: BitSet,
+}
+
+impl AccessSet {
+    pub fn new() -> Self {
+        Self {
+            reads: Vec::new(),
+            writes: Vec::new(),
+            read_bitset: BitSet::new(),
+            write_bitset: BitSet::new(),
+        }
+    }
+
+    pub fn read(&mut self, id: usize) {
+        if !self.read_bitset.test(id) {
+            self.reads.push(id);
+            self.read_bitset.set(id);
+        }
+    }
+
+    pub fn write(&mut self, id: usize) {
+        if !self.write_bitset.test(id) {
+            self.writes.push(id);
+            self.write_bitset.set(id);
+        }
+    }
+
+    pub fn extend(&mut self, other: &Self) {
+        for &id in other.reads.iter() {
+            self.read(id);
+        }
+
+        for &id in other.writes.iter() {
+            self.write(id);
+        }
+    }
+
+    /// Returns true if neither set writes to an ID that the other reads or writes
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.write_bitset.contains_none(&other.read_bitset)
+            && self.write_bitset.contains_none(&other.write_bitset)
+            && self.read_bitset.contains_none(&other.write_bitset)
+    }
+}
+
+/// Describes the components and resources that are read or written by a query or system, which is used
+/// to determine which systems can run in parallel.
+pub struct Access {
+    pub components: AccessSet,
+    /// Resources are identified by their index in the resource manager
+    pub resources: AccessSet,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self {
+            components: AccessSet::new(),
+            resources: AccessSet::new(),
+        }
+    }
+
+    pub fn read_component(&mut self, id: ComponentID) {
+        self.components.read(id);
+    }
+
+    pub fn write_component(&mut self, id: ComponentID) {
+        self.components.write(id);
+    }
+
+    pub fn read_resource(&mut self, index: usize) {
+        self.resources.read(index);
+    }
+
+    pub fn write_resource(&mut self, index: usize) {
+        self.resources.write(index);
+    }
+
+    pub fn extend(&mut self, other: &Self) {
+        self.components.extend(&other.components);
+        self.resources.extend(&other.resources);
+    }
+
+    /// Returns true if the two can be used at the same time, as neither writes to a component or resource
+    /// that the other reads or writes
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.components.is_compatible(&other.components)
+            && self.resources.is_compatible(&other.resources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatibility() {
+        let mut reader = Access::new();
+        reader.read_component(0);
+        reader.read_resource(0);
+
+        let mut other_reader = Access::new();
+        other_reader.read_component(0);
+        other_reader.write_component(1);
+
+        let mut writer = Access::new();
+        writer.write_component(0);
+
+        let mut resource_writer = Access::new();
+        resource_writer.write_resource(0);
+
+        assert!(reader.is_compatible(&other_reader));
+        assert!(!reader.is_compatible(&writer));
+        assert!(!writer.is_compatible(&reader));
+        assert!(!writer.is_compatible(&other_reader));
+        assert!(!reader.is_compatible(&resource_writer));
+        assert!(writer.is_compatible(&resource_writer));
+
+        reader.extend(&writer);
+        assert!(!reader.is_compatible(&other_reader));
+    }
+}
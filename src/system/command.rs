@@ -4,7 +4,11 @@ use core::{
     mem::{ManuallyDrop, MaybeUninit},
 };
 
-use crate::{component::Component, entity::Entity, World};
+use crate::{
+    component::{bundle::Bundle, Component},
+    entity::Entity,
+    World,
+};
 
 /// Stores commands to be executed on the world after the execution of all systems in a [Schedule]
 ///
@@ -37,6 +41,11 @@ impl CommandQueue {
         }
     }
 
+    /// Records that the component was modified, once the command is flushed. Components queried for with
+    /// `&mut C` record this as soon as they are mutably dereferenced, through a [Mut](crate::Mut).
+    ///
+    /// This has no effect where the component is not tracked, which can be avoided by registering it with
+    /// [World::register_component_tracked].
     pub fn flag_modified<C: Component>(&mut self, entity: Entity) {
         self.push(FlagModifiedCommand::<C>::new(entity));
     }
@@ -49,6 +58,23 @@ impl CommandQueue {
         self.push(RemoveComponentCommand::<C>::new(entity));
     }
 
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        self.push(InsertBundleCommand::new(entity, bundle));
+    }
+
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) {
+        self.push(RemoveBundleCommand::<B>::new(entity));
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) {
+        self.push(SpawnCommand::new(bundle));
+    }
+
+    /// The bundles are collected now, and spawned together when the queue is flushed.
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) {
+        self.push(SpawnBatchCommand::new(bundles.into_iter().collect()));
+    }
+
     pub fn add_entity(&mut self) {
         self.push(AddEntityCommand::new());
     }
@@ -151,6 +177,75 @@ impl<C: Component> Command for RemoveComponentCommand<C> {
     }
 }
 
+pub struct InsertBundleCommand<B: Bundle> {
+    entity: Entity,
+    bundle: B,
+}
+
+impl<B: Bundle> InsertBundleCommand<B> {
+    pub fn new(entity: Entity, bundle: B) -> Self {
+        Self { entity, bundle }
+    }
+}
+
+impl<B: Bundle> Command for InsertBundleCommand<B> {
+    fn execute(self, world: &mut World) {
+        world.insert_bundle(self.entity, self.bundle);
+    }
+}
+
+pub struct RemoveBundleCommand<B: Bundle> {
+    entity: Entity,
+    _marker: PhantomData<B>,
+}
+
+impl<B: Bundle> RemoveBundleCommand<B> {
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B: Bundle> Command for RemoveBundleCommand<B> {
+    fn execute(self, world: &mut World) {
+        world.remove_bundle::<B>(self.entity);
+    }
+}
+
+pub struct SpawnCommand<B: Bundle> {
+    bundle: B,
+}
+
+impl<B: Bundle> SpawnCommand<B> {
+    pub fn new(bundle: B) -> Self {
+        Self { bundle }
+    }
+}
+
+impl<B: Bundle> Command for SpawnCommand<B> {
+    fn execute(self, world: &mut World) {
+        world.spawn(self.bundle);
+    }
+}
+
+pub struct SpawnBatchCommand<B: Bundle> {
+    bundles: Vec<B>,
+}
+
+impl<B: Bundle> SpawnBatchCommand<B> {
+    pub fn new(bundles: Vec<B>) -> Self {
+        Self { bundles }
+    }
+}
+
+impl<B: Bundle> Command for SpawnBatchCommand<B> {
+    fn execute(self, world: &mut World) {
+        world.spawn_batch(self.bundles);
+    }
+}
+
 pub struct AddEntityCommand {}
 
 impl AddEntityCommand {
@@ -197,17 +292,24 @@ impl<C: Component> FlagModifiedCommand<C> {
 
 impl<C: Component> Command for FlagModifiedCommand<C> {
     fn execute(self, world: &mut World) {
+        if !world.entity_manager.alive(self.entity) {
+            return;
+        }
+
         let comp_id = world.component_manager.get_id::<C>();
         let entity_record = unsafe { world.entity_manager.get_record(self.entity) };
         let archetype = unsafe { world.archetype_manager.get_mut(entity_record.archetype_id) };
         let storage = unsafe { archetype.get_mut_storage(comp_id) };
 
-        debug_assert!(storage.is_tracked());
+        // Nothing can have seen changes to an untracked storage, so there is nothing to record
+        if !storage.is_tracked() {
+            return;
+        }
 
         let tracker = unsafe { storage.get_mut_tracker() };
         let info = unsafe { tracker.get_mut(entity_record.archetype_row) };
 
         info.modified = world.tick;
-        tracker.last_write = world.tick;
+        tracker.set_last_write(world.tick);
     }
 }
@@ -0,0 +1,99 @@
+use crate::{
+    component::Component,
+    event::EventReader,
+    resource::Resource,
+    system::{access::Access, command::CommandQueue, param::View},
+    World,
+};
+
+use super::{System, SystemParamFunction};
+
+/// A condition is a system that returns whether another system, or a whole
+/// [Schedule](super::schedule::Schedule), should run.
+///
+/// Conditions take [SystemParam](super::param::SystemParam)s just as systems do, and their access is
+/// counted as part of whatever they are attached to. Any commands they issue are added to the same queue.
+pub trait AnyCondition: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn access(&self) -> &Access;
+    fn initialize(&mut self, world: &mut World);
+    fn evaluate(&mut self, command_buffer: &mut CommandQueue, world: &mut World) -> bool;
+    /// # Safety
+    /// - See [System::run_unsafe]
+    unsafe fn evaluate_unsafe(&mut self, command_buffer: &mut CommandQueue, world: &World) -> bool;
+    fn sync(&mut self, world: &mut World);
+    fn check_ticks(&mut self, this_run: u32);
+}
+
+impl<M, F: SystemParamFunction<M, Out = bool>> AnyCondition for System<M, F> {
+    fn name(&self) -> &'static str {
+        System::name(self)
+    }
+
+    fn access(&self) -> &Access {
+        System::access(self)
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        System::initialize(self, world)
+    }
+
+    fn evaluate(&mut self, command_buffer: &mut CommandQueue, world: &mut World) -> bool {
+        System::run(self, command_buffer, world)
+    }
+
+    unsafe fn evaluate_unsafe(&mut self, command_buffer: &mut CommandQueue, world: &World) -> bool {
+        // SAFETY: Deferred to the caller
+        unsafe { System::run_unsafe(self, command_buffer, world) }
+    }
+
+    fn sync(&mut self, world: &mut World) {
+        System::sync(self, world)
+    }
+
+    fn check_ticks(&mut self, this_run: u32) {
+        System::check_ticks(self, this_run)
+    }
+}
+
+/// Conversion into a condition. This is implemented for any function that is a [SystemParamFunction]
+/// returning `bool`.
+pub trait IntoCondition<M> {
+    type Condition: AnyCondition + 'static;
+
+    fn into_condition(self) -> Self::Condition;
+}
+
+impl<M: 'static, F: SystemParamFunction<M, Out = bool>> IntoCondition<M> for F {
+    type Condition = System<M, F>;
+
+    fn into_condition(self) -> Self::Condition {
+        System::from_fn(self)
+    }
+}
+
+/// A condition that is met while the resource is equal to the value
+///
+/// # Panics
+/// - If the resource has not been added to the world, when the condition is initialised
+pub fn resource_equals<R: Resource + PartialEq>(value: R) -> impl FnMut(&R) -> bool {
+    move |resource: &R| *resource == value
+}
+
+/// A condition that is met while any entity has the component
+pub fn any_with_component<C: Component>() -> impl FnMut(View<&C>) -> bool {
+    |view: View<&C>| view.iter().next().is_some()
+}
+
+/// A condition that is met when there are events of the type that have been pushed since the condition was
+/// last evaluated
+///
+/// # Panics
+/// - If the event type has not been registered, when the condition is initialised
+pub fn on_event<E: Send + Sync + 'static>() -> impl FnMut(EventReader<E>) -> bool {
+    |mut reader: EventReader<E>| {
+        let met = !reader.is_empty();
+        reader.clear();
+        met
+    }
+}
@@ -0,0 +1,94 @@
+use super::{
+    condition::{AnyCondition, IntoCondition},
+    AnySystem, IntoSystem,
+};
+
+/// Identifies a system, or a group of systems sharing the label, so that other systems can be ordered
+/// relative to it
+pub type SystemLabel = &'static str;
+
+/// A system along with its labels and ordering constraints, ready to be added to a
+/// [ScheduleBuilder](super::schedule::ScheduleBuilder)
+pub struct SystemConfig {
+    pub(crate) system: Box<dyn AnySystem>,
+    pub(crate) labels: Vec<SystemLabel>,
+    pub(crate) before: Vec<SystemLabel>,
+    pub(crate) after: Vec<SystemLabel>,
+    pub(crate) conditions: Vec<Box<dyn AnyCondition>>,
+}
+
+impl SystemConfig {
+    pub fn new(system: Box<dyn AnySystem>) -> Self {
+        Self {
+            system,
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            conditions: Vec::new(),
+        }
+    }
+}
+
+/// Conversion into a [SystemConfig]. This is implemented for anything that is [IntoSystem], so that
+/// ordering constraints can be declared directly on a system function.
+///
+/// A constraint on a label that no system has is ignored, as the system it refers to may simply not have
+/// been added to the same schedule.
+pub trait IntoSystemConfig<M> {
+    fn into_config(self) -> SystemConfig;
+
+    /// Adds a label to the system. Many systems can share the same label.
+    fn label(self, label: SystemLabel) -> SystemConfig
+    where
+        Self: Sized,
+    {
+        let mut config = self.into_config();
+        config.labels.push(label);
+        config
+    }
+
+    /// The system will run before every system with the label
+    fn before(self, label: SystemLabel) -> SystemConfig
+    where
+        Self: Sized,
+    {
+        let mut config = self.into_config();
+        config.before.push(label);
+        config
+    }
+
+    /// The system will run after every system with the label
+    fn after(self, label: SystemLabel) -> SystemConfig
+    where
+        Self: Sized,
+    {
+        let mut config = self.into_config();
+        config.after.push(label);
+        config
+    }
+
+    /// Adds a condition that must be met for the system to run
+    fn run_if<MC>(self, condition: impl IntoCondition<MC>) -> SystemConfig
+    where
+        Self: Sized,
+    {
+        let mut config = self.into_config();
+        config.conditions.push(Box::new(condition.into_condition()));
+        config
+    }
+}
+
+/// Marker for [IntoSystemConfig] on a [SystemConfig]
+pub struct ConfigMarker;
+
+impl IntoSystemConfig<ConfigMarker> for SystemConfig {
+    fn into_config(self) -> SystemConfig {
+        self
+    }
+}
+
+impl<M, S: IntoSystem<M>> IntoSystemConfig<M> for S {
+    fn into_config(self) -> SystemConfig {
+        SystemConfig::new(Box::new(self.into_system()))
+    }
+}
@@ -1,5 +1,10 @@
 mod system;
 pub use system::*;
 
+pub mod access;
 pub mod command;
+pub mod condition;
+pub mod config;
+pub mod param;
+pub mod pool;
 pub mod schedule;
@@ -0,0 +1,504 @@
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    component::tracking::clamp_tick,
+    entity::Entity,
+    query::{
+        bundle::{ComponentBundle, FilterBundle, ReadOnlyComponentBundle, ResourceBundle},
+        filter::FilterBuilder,
+        iter::{ComponentBundleIter, ComponentBundleParIter},
+        Query, QueryEntityError,
+    },
+    resource::{Resource, ResourceId},
+    World,
+};
+
+use super::{access::Access, command::CommandQueue};
+
+/// Information about a system that is shared with each of its parameters
+pub struct SystemMeta {
+    pub(crate) name: &'static str,
+
+    /// The world tick at which the system last ran
+    pub(crate) last_run: u32,
+
+    /// The components and resources accessed by all of the system's parameters
+    pub(crate) access: Access,
+
+    /// Whether a parameter has already taken the command queue
+    pub(crate) commands: bool,
+}
+
+impl SystemMeta {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            last_run: 0,
+            access: Access::new(),
+            commands: false,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn last_run(&self) -> u32 {
+        self.last_run
+    }
+
+    /// Clamps the tick that the system last ran at, if it is becoming too old to be compared with the
+    /// current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        clamp_tick(&mut self.last_run, this_run);
+    }
+
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+
+    /// Adds the access of a parameter to that of the system
+    ///
+    /// # Panics
+    /// - If the access conflicts with that of another parameter of the system, as they would otherwise
+    ///   alias each other
+    pub fn add_access(&mut self, access: &Access) {
+        #[cold]
+        #[inline(never)]
+        #[track_caller]
+        fn assert_failed(name: &str) -> ! {
+            panic!(
+                "System {:?} has parameters that conflict in their access to a component or resource",
+                name
+            );
+        }
+
+        if !self.access.is_compatible(access) {
+            assert_failed(self.name);
+        }
+
+        self.access.extend(access);
+    }
+
+    /// Records that a parameter takes the command queue
+    ///
+    /// # Panics
+    /// - If another parameter of the system has already taken it, as they would otherwise alias each other
+    pub fn add_commands_access(&mut self) {
+        #[cold]
+        #[inline(never)]
+        #[track_caller]
+        fn assert_failed(name: &str) -> ! {
+            panic!(
+                "System {:?} has more than one parameter that takes the command queue",
+                name
+            );
+        }
+
+        if self.commands {
+            assert_failed(self.name);
+        }
+
+        self.commands = true;
+    }
+}
+
+/// A SystemParam is anything that a system function can take as one of its parameters, such as a [View]
+/// over a set of components, a resource, or the [Commands] of the schedule.
+///
+/// # Implementation
+/// Each parameter has some persistent state which is created when the system is first added to a world,
+/// and is kept between runs. Before each run, the parameter is fetched from this state and the world.
+/// This is where the lifetimes of [SystemParam::Item] come from - `'w` borrows from the world, and `'s`
+/// from the state.
+pub trait SystemParam {
+    /// The state kept by the system for this parameter, between runs
+    type State: Send + Sync + 'static;
+    /// The concrete type of the parameter, but with the lifetimes of the world and state
+    type Item<'w, 's>;
+
+    /// Creates the state for this parameter
+    fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State;
+
+    /// Adds the components and resources accessed by this parameter to the system, with
+    /// [SystemMeta::add_access]
+    fn init_access(_state: &Self::State, _meta: &mut SystemMeta) {}
+
+    /// Updates the state to account for changes to the world, such as newly created archetypes
+    fn sync(_state: &mut Self::State, _world: &mut World) {}
+
+    /// # Safety
+    /// - The command queue must be valid for `'w`, and not be borrowed anywhere else for that duration.
+    /// - Any data that this parameter accesses mutably must not be borrowed anywhere else for `'w`.
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        meta: &SystemMeta,
+        world: &'w World,
+        commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's>;
+}
+
+/// Shorthand for the [SystemParam::Item] of a parameter
+pub type SystemParamItem<'w, 's, P> = <P as SystemParam>::Item<'w, 's>;
+
+/// A system parameter that provides access to all entities matching a set of components, and an optional
+/// set of filters.
+///
+/// It is backed by a [Query] that is kept as the parameter's state, so the matching archetypes are cached
+/// and kept up to date between runs.
+///
+/// Any [Added](crate::Added) or [Changed](crate::Changed) filters match components that were added or
+/// changed since the system last ran.
+pub struct View<'w, 's, C: ComponentBundle, F: FilterBundle = ()> {
+    query: &'s Query<C, ()>,
+    world: &'w World,
+    last_run: u32,
+    _marker: PhantomData<F>,
+}
+
+impl<'w, 's, C: ComponentBundle, F: FilterBundle> View<'w, 's, C, F> {
+    pub fn iter(&self) -> ComponentBundleIter<'w, 's, C>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        self.query.iter_since(self.world, self.last_run)
+    }
+
+    /// Returns an iterator over the items, borrowing the view exclusively so that the items can be modified
+    /// without aliasing any others
+    pub fn iter_mut(&mut self) -> ComponentBundleIter<'_, 's, C> {
+        // SAFETY: The system has access to the components, and the view is borrowed exclusively for as long
+        //         as the items live
+        unsafe { self.query.iter_unchecked(self.world, self.last_run) }
+    }
+
+    /// Returns an iterator that handles batches of items on separate threads
+    pub fn par_iter(&self) -> ComponentBundleParIter<'w, 's, C>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        self.query.par_iter_since(self.world, self.last_run)
+    }
+
+    /// Returns an iterator that handles batches of items on separate threads, borrowing the view
+    /// exclusively so that the items can be modified without aliasing any others
+    pub fn par_iter_mut(&mut self) -> ComponentBundleParIter<'_, 's, C> {
+        // SAFETY: The system has access to the components, and the view is borrowed exclusively for as long
+        //         as the items live
+        unsafe { self.query.par_iter_unchecked(self.world, self.last_run) }
+    }
+
+    /// Returns the items of a single entity, where any change filters match components that changed since
+    /// the system last ran
+    pub fn get(&self, entity: Entity) -> Result<C::Item<'w>, QueryEntityError>
+    where
+        C: ReadOnlyComponentBundle,
+    {
+        self.query.get_since(self.world, entity, self.last_run)
+    }
+
+    /// Returns the items of a single entity, borrowing the view exclusively so that the items can be
+    /// modified without aliasing any others
+    pub fn get_mut(&mut self, entity: Entity) -> Result<C::Item<'_>, QueryEntityError> {
+        // SAFETY: The system has access to the components, and the view is borrowed exclusively for as long
+        //         as the items live
+        unsafe { self.query.get_unchecked(self.world, entity, self.last_run) }
+    }
+
+    pub fn query(&self) -> &'s Query<C, ()> {
+        self.query
+    }
+}
+
+impl<'w, 's, C: ComponentBundle, F: FilterBundle> IntoIterator for View<'w, 's, C, F> {
+    type Item = C::Item<'w>;
+    type IntoIter = ComponentBundleIter<'w, 's, C>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        // SAFETY: The system has access to the components, and the view is consumed by the iterator
+        unsafe { self.query.iter_unchecked(self.world, self.last_run) }
+    }
+}
+
+impl<C: ComponentBundle + 'static, F: FilterBundle> SystemParam for View<'_, '_, C, F> {
+    type State = Query<C, ()>;
+    type Item<'w, 's> = View<'w, 's, C, F>;
+
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        let filter_ids = F::parameter_ids(&world.component_manager);
+        let filter_builder = F::build_filter(FilterBuilder::with_capacity(C::count()), &filter_ids);
+
+        Query::new(
+            &world.component_manager,
+            &world.resource_manager,
+            &mut world.archetype_manager,
+            filter_builder,
+        )
+    }
+
+    fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+        meta.add_access(&state.filter.access);
+    }
+
+    fn sync(state: &mut Self::State, world: &mut World) {
+        state.sync(world);
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        View {
+            query: state,
+            world,
+            last_run: meta.last_run,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A [Query] can be used directly as a parameter, in which case it is fetched as an iterator over its
+/// components, along with its resources.
+///
+/// This is what systems created with [System::new](super::System::new) use.
+impl<C: ComponentBundle + 'static, R: ResourceBundle> SystemParam for Query<C, R> {
+    type State = Query<C, R>;
+    type Item<'w, 's> = (ComponentBundleIter<'w, 's, C>, R::Item<'w>);
+
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        Query::new(
+            &world.component_manager,
+            &world.resource_manager,
+            &mut world.archetype_manager,
+            FilterBuilder::with_capacity(C::count()),
+        )
+    }
+
+    fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+        let mut resource_access = Access::new();
+        R::update_access(&state.res_param_ids, &mut resource_access);
+
+        meta.add_access(&state.filter.access);
+        meta.add_access(&resource_access);
+    }
+
+    fn sync(state: &mut Self::State, world: &mut World) {
+        state.sync(world);
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        // SAFETY: The resource IDs were retrieved from the resource manager when the query was built
+        let resources =
+            unsafe { R::fetch_item(&world.resource_manager.resources, state.res_param_ids) };
+
+        // SAFETY: The system has access to the components, and only one iterator is fetched per run
+        let iter = unsafe { state.iter_unchecked(world, meta.last_run) };
+
+        (iter, resources)
+    }
+}
+
+impl<R: Resource> SystemParam for &R {
+    type State = ResourceId<R>;
+    type Item<'w, 's> = &'w R;
+
+    /// # Panics
+    /// - If the resource has not been added to the world
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        world.get_resource_id::<R>()
+    }
+
+    fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+        let mut access = Access::new();
+        access.read_resource(state.index);
+        meta.add_access(&access);
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        // SAFETY: The ID was retrieved from the resource manager, and we do not remove resources
+        unsafe { world.get_resource_unchecked(*state) }
+    }
+}
+
+impl<R: Resource> SystemParam for &mut R {
+    type State = ResourceId<R>;
+    type Item<'w, 's> = &'w mut R;
+
+    /// # Panics
+    /// - If the resource has not been added to the world
+    fn init_state(world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        world.get_resource_id::<R>()
+    }
+
+    fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+        let mut access = Access::new();
+        access.write_resource(state.index);
+        meta.add_access(&access);
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        // SAFETY:
+        // - The ID was retrieved from the resource manager, and we do not remove resources
+        // - Caller ensures the resource is not borrowed elsewhere
+        unsafe { world.get_mut_resource_unchecked(*state) }
+    }
+}
+
+/// A system parameter that provides the [CommandQueue] of the [Schedule](super::schedule::Schedule)
+/// that the system is running in.
+///
+/// A system may only take one of these, as it borrows the queue mutably.
+pub struct Commands<'w> {
+    queue: &'w mut CommandQueue,
+}
+
+impl Deref for Commands<'_> {
+    type Target = CommandQueue;
+
+    fn deref(&self) -> &Self::Target {
+        self.queue
+    }
+}
+
+impl DerefMut for Commands<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.queue
+    }
+}
+
+impl SystemParam for Commands<'_> {
+    type State = ();
+    type Item<'w, 's> = Commands<'w>;
+
+    fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {}
+
+    fn init_access(_state: &Self::State, meta: &mut SystemMeta) {
+        meta.add_commands_access();
+    }
+
+    unsafe fn fetch<'w, 's>(
+        _state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        _world: &'w World,
+        commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        // SAFETY: Caller ensures the queue is valid for 'w, and not borrowed elsewhere. Only one Commands
+        //         parameter is allowed per system, so no other parameter borrows it either.
+        Commands {
+            queue: unsafe { &mut *commands },
+        }
+    }
+}
+
+/// A system parameter holding a value that is local to the system, and persists between runs. It starts
+/// out as the [Default] value of the type.
+pub struct Local<'s, T: Default + Send + Sync + 'static> {
+    value: &'s mut T,
+}
+
+impl<T: Default + Send + Sync + 'static> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<T: Default + Send + Sync + 'static> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value
+    }
+}
+
+impl<T: Default + Send + Sync + 'static> SystemParam for Local<'_, T> {
+    type State = T;
+    type Item<'w, 's> = Local<'s, T>;
+
+    fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {
+        T::default()
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        _world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+        Local { value: state }
+    }
+}
+
+impl SystemParam for () {
+    type State = ();
+    type Item<'w, 's> = ();
+
+    fn init_state(_world: &mut World, _meta: &mut SystemMeta) -> Self::State {}
+
+    unsafe fn fetch<'w, 's>(
+        _state: &'s mut Self::State,
+        _meta: &SystemMeta,
+        _world: &'w World,
+        _commands: *mut CommandQueue,
+    ) -> Self::Item<'w, 's> {
+    }
+}
+
+macro_rules! impl_system_param_tuple {
+    ($($P:ident $i:tt),+) => {
+        impl<$($P: SystemParam),+> SystemParam for ($($P,)+) {
+            type State = ($($P::State,)+);
+            type Item<'w, 's> = ($($P::Item<'w, 's>,)+);
+
+            fn init_state(world: &mut World, meta: &mut SystemMeta) -> Self::State {
+                ($($P::init_state(world, meta),)+)
+            }
+
+            fn init_access(state: &Self::State, meta: &mut SystemMeta) {
+                $($P::init_access(&state.$i, meta);)+
+            }
+
+            fn sync(state: &mut Self::State, world: &mut World) {
+                $($P::sync(&mut state.$i, world);)+
+            }
+
+            unsafe fn fetch<'w, 's>(
+                state: &'s mut Self::State,
+                meta: &SystemMeta,
+                world: &'w World,
+                commands: *mut CommandQueue,
+            ) -> Self::Item<'w, 's> {
+                // SAFETY: Deferred to the caller
+                ($(unsafe { $P::fetch(&mut state.$i, meta, world, commands) },)+)
+            }
+        }
+    };
+}
+
+impl_system_param_tuple!(P0 0);
+impl_system_param_tuple!(P0 0, P1 1);
+impl_system_param_tuple!(P0 0, P1 1, P2 2);
+impl_system_param_tuple!(P0 0, P1 1, P2 2, P3 3);
+impl_system_param_tuple!(P0 0, P1 1, P2 2, P3 3, P4 4);
+impl_system_param_tuple!(P0 0, P1 1, P2 2, P3 3, P4 4, P5 5);
+impl_system_param_tuple!(P0 0, P1 1, P2 2, P3 3, P4 4, P5 5, P6 6);
+impl_system_param_tuple!(P0 0, P1 1, P2 2, P3 3, P4 4, P5 5, P6 6, P7 7);
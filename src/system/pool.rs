@@ -0,0 +1,257 @@
+use core::{
+    any::Any,
+    marker::PhantomData,
+    mem,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of worker threads that jobs are handed to, so that running systems in parallel does not
+/// spawn new threads every time.
+///
+/// # Implementation
+/// Workers share a single queue of jobs. Jobs are only ever given out through a [Scope], which waits for
+/// all of its jobs to finish before returning, so they are allowed to borrow from the caller. While
+/// waiting, the calling thread runs queued jobs too, meaning a pool without any workers still makes
+/// progress, and a job that opens a scope of its own can't deadlock the pool.
+pub struct TaskPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+
+    /// Notified whenever a job is queued, a scope's last job finishes, or the pool is closed
+    changed: Condvar,
+}
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    closed: bool,
+}
+
+impl TaskPool {
+    /// Creates a pool with the given number of worker threads, in addition to whichever thread opens a
+    /// scope
+    pub fn new(threads: usize) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                jobs: VecDeque::new(),
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let workers = (0..threads)
+            .map(|i| {
+                let shared = shared.clone();
+                thread::Builder::new()
+                    .name(format!("ecs worker {}", i))
+                    .spawn(move || shared.work())
+                    .expect("failed to spawn worker thread")
+            })
+            .collect();
+
+        Self { shared, workers }
+    }
+
+    /// The number of worker threads, not including the thread that opens a scope
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Calls the function with a [Scope] that jobs can be spawned on, then waits for every job to finish.
+    ///
+    /// # Panics
+    /// - If the function or any of the jobs panicked, once every job has finished
+    pub fn scope<'scope, F, R>(&'scope self, func: F) -> R
+    where
+        F: FnOnce(&Scope<'scope>) -> R,
+    {
+        let scope = Scope {
+            shared: &self.shared,
+            state: Arc::new(ScopeState {
+                pending: AtomicUsize::new(0),
+                panic: Mutex::new(None),
+            }),
+            _marker: PhantomData,
+        };
+
+        // Jobs may borrow from the caller, so they must finish before we return, even by unwinding
+        let result = panic::catch_unwind(AssertUnwindSafe(|| func(&scope)));
+        scope.wait();
+
+        let result = match result {
+            Ok(result) => result,
+            Err(payload) => panic::resume_unwind(payload),
+        };
+
+        if let Some(payload) = scope.state.panic.lock().unwrap().take() {
+            panic::resume_unwind(payload);
+        }
+
+        result
+    }
+}
+
+impl Drop for TaskPool {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Shared {
+    /// Runs queued jobs until the pool is closed
+    fn work(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                job();
+                queue = self.queue.lock().unwrap();
+            } else if queue.closed {
+                return;
+            } else {
+                queue = self.changed.wait(queue).unwrap();
+            }
+        }
+    }
+}
+
+struct ScopeState {
+    /// The number of jobs that have been spawned but not finished. This is only decremented while the
+    /// queue is locked, so that a thread waiting on it can't miss the notification.
+    pending: AtomicUsize,
+
+    /// The payload of the first job to panic
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Spawns jobs onto a [TaskPool], which may borrow anything that outlives `'scope`
+pub struct Scope<'scope> {
+    shared: &'scope Arc<Shared>,
+    state: Arc<ScopeState>,
+
+    /// Invariant over `'scope`, as with [std::thread::Scope]
+    _marker: PhantomData<&'scope mut &'scope ()>,
+}
+
+impl<'scope> Scope<'scope> {
+    /// Queues the function to be run by the pool
+    pub fn spawn<F>(&self, func: F)
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        self.state.pending.fetch_add(1, Ordering::Relaxed);
+
+        let shared = self.shared.clone();
+        let state = self.state.clone();
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(func)) {
+                state.panic.lock().unwrap().get_or_insert(payload);
+            }
+
+            let _queue = shared.queue.lock().unwrap();
+            if state.pending.fetch_sub(1, Ordering::Release) == 1 {
+                shared.changed.notify_all();
+            }
+        });
+
+        // SAFETY: `TaskPool::scope` does not return until this job has finished, so nothing it borrows for
+        //         `'scope` is used after it ends
+        let job: Job = unsafe { mem::transmute(job) };
+
+        self.shared.queue.lock().unwrap().jobs.push_back(job);
+        self.shared.changed.notify_all();
+    }
+
+    /// Blocks until every job spawned on this scope has finished, running queued jobs in the meantime
+    fn wait(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        loop {
+            if self.state.pending.load(Ordering::Acquire) == 0 {
+                return;
+            }
+
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                job();
+                queue = self.shared.queue.lock().unwrap();
+            } else {
+                // Any jobs that are left are being run by other threads
+                queue = self.shared.changed.wait(queue).unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_borrows_and_waits() {
+        let pool = TaskPool::new(3);
+        let mut values = vec![0; 64];
+
+        // Run several scopes on the same workers, each borrowing from this stack frame
+        for _ in 0..4 {
+            pool.scope(|scope| {
+                for chunk in values.chunks_mut(8) {
+                    scope.spawn(move || chunk.iter_mut().for_each(|value| *value += 1));
+                }
+            });
+        }
+
+        assert!(values.iter().all(|&value| value == 4));
+    }
+
+    #[test]
+    fn no_workers_and_nested_scopes() {
+        let count = AtomicUsize::new(0);
+
+        for pool in [TaskPool::new(0), TaskPool::new(1)] {
+            pool.scope(|scope| {
+                for _ in 0..4 {
+                    scope.spawn(|| {
+                        pool.scope(|scope| {
+                            for _ in 0..4 {
+                                scope.spawn(|| {
+                                    count.fetch_add(1, Ordering::Relaxed);
+                                });
+                            }
+                        });
+                    });
+                }
+            });
+        }
+
+        assert_eq!(count.load(Ordering::Relaxed), 32);
+    }
+
+    #[test]
+    #[should_panic(expected = "job failed")]
+    fn job_panic_is_resumed() {
+        let pool = TaskPool::new(2);
+
+        pool.scope(|scope| {
+            scope.spawn(|| panic!("job failed"));
+            for _ in 0..4 {
+                scope.spawn(|| {});
+            }
+        });
+    }
+}
@@ -1,9 +1,100 @@
-use crate::{
-    query::bundle::{ComponentBundle, ResourceBundle},
-    World,
+use core::{cmp::Reverse, fmt, time::Duration};
+use std::collections::BinaryHeap;
+
+use crate::World;
+
+use super::{
+    access::Access,
+    command::CommandQueue,
+    condition::{AnyCondition, IntoCondition},
+    config::{IntoSystemConfig, SystemConfig},
+    AnySystem, IntoSystem,
 };
 
-use super::{command::CommandQueue, AnySystem, System};
+/// Determines how the systems of a [Schedule] are run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExecutorKind {
+    /// Systems are run one after another on the calling thread, in the order given by their ordering
+    /// constraints, then insertion order
+    #[default]
+    SingleThreaded,
+
+    /// Systems whose access to components and resources does not conflict are run at the same time, on
+    /// separate threads. Systems that do conflict are still run in the order given by their ordering
+    /// constraints, then insertion order.
+    ///
+    /// This must be chosen with [ScheduleBuilder::executor], as systems are run on a single thread by
+    /// default.
+    MultiThreaded,
+}
+
+/// Identifies a [Schedule] that has been added to a [World], so that it can be run on demand
+pub type ScheduleLabel = &'static str;
+
+/// Determines how often a [Schedule] that has been added to a [World] is run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScheduleRate {
+    /// Run once, on the first update after being added, before any other schedule
+    Startup,
+
+    /// Run once on every update
+    #[default]
+    EveryUpdate,
+
+    /// Run once for every timestep of this length that has passed, as measured by the
+    /// [Time](crate::Time) resource. Time that is left over is carried into the next update, so this may
+    /// run several times in one update, or not at all. It runs at most
+    /// [Time::max_fixed_steps](crate::Time::max_fixed_steps) times in one update, and any time left over
+    /// beyond that is dropped.
+    Fixed(Duration),
+}
+
+impl ScheduleRate {
+    /// A [ScheduleRate::Fixed] rate that runs the given number of times per second
+    ///
+    /// # Panics
+    /// - If the frequency is not greater than zero, or is so small that its timestep can't be represented
+    pub fn from_hz(hz: f64) -> Self {
+        #[cold]
+        #[inline(never)]
+        #[track_caller]
+        fn assert_failed(hz: f64) -> ! {
+            panic!(
+                "A fixed rate must be greater than zero, but it is {} Hz",
+                hz
+            );
+        }
+
+        match Duration::try_from_secs_f64(1.0 / hz) {
+            Ok(step) if hz > 0.0 => Self::Fixed(step),
+            _ => assert_failed(hz),
+        }
+    }
+}
+
+/// An error from building a [Schedule]
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The ordering constraints of these systems contradict each other. Each system must run before the
+    /// next, and the last must run before the first.
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cycle(names) => {
+                write!(f, "systems have contradicting order constraints: ")?;
+                for name in names.iter() {
+                    write!(f, "{} -> ", name)?;
+                }
+                write!(f, "{}", names[0])
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
 
 /// A builder for [Schedule]s
 ///
@@ -11,60 +102,691 @@ use super::{command::CommandQueue, AnySystem, System};
 /// Unlike with commands in [CommandQueue]s, here we store systems as heap allocated trait objects as there
 /// are fewer of them and are expected to stick around unlike commands which are ephemeral.
 ///
+/// Systems are ordered by their constraints when the schedule is built. Where systems are not constrained
+/// relative to each other, they keep the order in which they were added.
 pub struct ScheduleBuilder {
-    systems: Vec<Box<dyn AnySystem>>,
+    systems: Vec<SystemConfig>,
+    conditions: Vec<Box<dyn AnyCondition>>,
+    executor: ExecutorKind,
 }
 
 impl ScheduleBuilder {
     pub fn new() -> Self {
         Self {
             systems: Vec::new(),
+            conditions: Vec::new(),
+            executor: ExecutorKind::default(),
         }
     }
 
-    pub fn add<C: ComponentBundle, R: ResourceBundle>(mut self, system: System<C, R>) -> Self {
-        self.systems.push(Box::new(system));
+    pub fn add<M>(mut self, system: impl IntoSystemConfig<M>) -> Self {
+        self.systems.push(system.into_config());
         self
     }
 
-    pub fn build(self) -> Schedule {
-        Schedule::new(self.systems, CommandQueue::new())
+    /// Adds a condition that must be met for any of the systems in the schedule to run
+    pub fn run_if<M>(mut self, condition: impl IntoCondition<M>) -> Self {
+        self.conditions.push(Box::new(condition.into_condition()));
+        self
+    }
+
+    pub fn executor(mut self, executor: ExecutorKind) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Sorts the systems so that every ordering constraint is met
+    ///
+    /// # Errors
+    /// - [ScheduleError::Cycle] if the constraints contradict each other
+    pub fn build(self) -> Result<Schedule, ScheduleError> {
+        let count = self.systems.len();
+
+        // For each system, the systems that must run after it
+        let mut successors = vec![Vec::new(); count];
+        for (index, config) in self.systems.iter().enumerate() {
+            for (other, other_config) in self.systems.iter().enumerate() {
+                let before = config
+                    .before
+                    .iter()
+                    .any(|label| other_config.labels.contains(label));
+                let after = other_config
+                    .after
+                    .iter()
+                    .any(|label| config.labels.contains(label));
+
+                if before || after {
+                    successors[index].push(other);
+                }
+            }
+        }
+
+        let order = topological_sort(&successors).map_err(|cycle| {
+            let names = cycle
+                .into_iter()
+                .map(|index| self.systems[index].system.name())
+                .collect();
+            ScheduleError::Cycle(names)
+        })?;
+
+        let mut positions = vec![0; count];
+        for (position, &index) in order.iter().enumerate() {
+            positions[index] = position;
+        }
+
+        let mut dependencies = vec![Vec::new(); count];
+        for (index, successors) in successors.iter().enumerate() {
+            for &successor in successors.iter() {
+                dependencies[positions[successor]].push(positions[index]);
+            }
+        }
+
+        let mut configs: Vec<_> = self.systems.into_iter().map(Some).collect();
+        let systems = order
+            .iter()
+            .zip(dependencies)
+            .map(|(&index, dependencies)| {
+                // SAFETY: Each index appears in the order exactly once
+                let config = unsafe { configs[index].take().unwrap_unchecked() };
+
+                ScheduledSystem {
+                    system: config.system,
+                    conditions: config.conditions,
+                    commands: CommandQueue::new(),
+                    dependencies,
+                }
+            })
+            .collect();
+
+        Ok(Schedule {
+            systems,
+            conditions: self.conditions,
+            commands: CommandQueue::new(),
+            executor: self.executor,
+            batches: None,
+        })
     }
 }
 
+/// Sorts the nodes of the graph such that each node comes before all of its successors. Where there is a
+/// choice, nodes with a lower index come first.
+///
+/// Returns a cycle in the graph, if there is one, as a list of nodes starting from the lowest, where each
+/// is a predecessor of the next, and the last is a predecessor of the first.
+fn topological_sort(successors: &[Vec<usize>]) -> Result<Vec<usize>, Vec<usize>> {
+    let mut predecessor_counts = vec![0; successors.len()];
+    for &successor in successors.iter().flatten() {
+        predecessor_counts[successor] += 1;
+    }
+
+    let mut ready: BinaryHeap<_> = (0..successors.len())
+        .filter(|&node| predecessor_counts[node] == 0)
+        .map(Reverse)
+        .collect();
+
+    let mut order = Vec::with_capacity(successors.len());
+    while let Some(Reverse(node)) = ready.pop() {
+        order.push(node);
+
+        for &successor in successors[node].iter() {
+            predecessor_counts[successor] -= 1;
+            if predecessor_counts[successor] == 0 {
+                ready.push(Reverse(successor));
+            }
+        }
+    }
+
+    if order.len() == successors.len() {
+        return Ok(order);
+    }
+
+    // Every node that was not sorted has a predecessor that was also not sorted, so walking backwards
+    // through these will eventually revisit a node
+    let mut path = Vec::new();
+    let mut node = (0..successors.len())
+        .find(|&node| predecessor_counts[node] > 0)
+        .unwrap();
+
+    while !path.contains(&node) {
+        path.push(node);
+        node = (0..successors.len())
+            .find(|&other| predecessor_counts[other] > 0 && successors[other].contains(&node))
+            .unwrap();
+    }
+
+    let start = path.iter().position(|&other| other == node).unwrap();
+    let mut cycle = path.split_off(start);
+    cycle.reverse();
+
+    // Start from the lowest node, so the same cycle is always reported the same way
+    let lowest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+    cycle.rotate_left(lowest);
+
+    Err(cycle)
+}
+
+/// A system in a [Schedule], along with everything the schedule keeps for it
+struct ScheduledSystem {
+    system: Box<dyn AnySystem>,
+    conditions: Vec<Box<dyn AnyCondition>>,
+    commands: CommandQueue,
+
+    /// The indices of the systems that this one is constrained to run after
+    dependencies: Vec<usize>,
+}
+
+impl ScheduledSystem {
+    fn new(system: Box<dyn AnySystem>) -> Self {
+        Self {
+            system,
+            conditions: Vec::new(),
+            commands: CommandQueue::new(),
+            dependencies: Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        self.system.initialize(world);
+        for condition in self.conditions.iter_mut() {
+            condition.initialize(world);
+        }
+    }
+
+    /// The combined access of the system and its conditions
+    fn access(&self) -> Access {
+        let mut access = Access::new();
+        access.extend(self.system.access());
+        for condition in self.conditions.iter() {
+            access.extend(condition.access());
+        }
+
+        access
+    }
+
+    fn run(&mut self, world: &mut World) {
+        let commands = &mut self.commands;
+        if self
+            .conditions
+            .iter_mut()
+            .all(|condition| condition.evaluate(commands, world))
+        {
+            self.system.run(commands, world);
+        }
+    }
+
+    /// # Safety
+    /// - See [System::run_unsafe](super::System::run_unsafe)
+    unsafe fn run_unsafe(&mut self, world: &World) {
+        let commands = &mut self.commands;
+
+        // SAFETY: Deferred to the caller, as the access of the conditions is part of that of the system
+        unsafe {
+            if self
+                .conditions
+                .iter_mut()
+                .all(|condition| condition.evaluate_unsafe(commands, world))
+            {
+                self.system.run_unsafe(commands, world);
+            }
+        }
+    }
+
+    fn sync(&mut self, world: &mut World) {
+        self.system.sync(world);
+        for condition in self.conditions.iter_mut() {
+            condition.sync(world);
+        }
+    }
+
+    fn check_ticks(&mut self, this_run: u32) {
+        self.system.check_ticks(this_run);
+        for condition in self.conditions.iter_mut() {
+            condition.check_ticks(this_run);
+        }
+    }
+}
+
+/// A collection of systems that are run together, followed by the commands they issued.
+///
+/// # Implementation
+/// Each system has its own [CommandQueue], so that systems running at the same time do not need to
+/// synchronise when issuing commands. The queues are flushed in the order given by the systems' ordering
+/// constraints, then insertion order.
+///
+/// When run with [ExecutorKind::MultiThreaded], the systems are grouped into batches, such that systems
+/// in the same batch have compatible access and can run in parallel. A system is placed in the batch
+/// after the latest one containing a system, ordered before it, that it conflicts with or is constrained
+/// to run after. Batches are run one after another, on the worker threads that the world keeps for this.
+///
+/// Conditions are evaluated just before whatever they are attached to would run. Systems that do not run
+/// are still synced, so they are up to date whenever they do run.
 pub struct Schedule {
-    systems: Vec<Box<dyn AnySystem>>,
+    systems: Vec<ScheduledSystem>,
+
+    /// Conditions for the whole schedule, along with the queue for any commands they issue
+    conditions: Vec<Box<dyn AnyCondition>>,
     commands: CommandQueue,
+
+    executor: ExecutorKind,
+
+    /// Indices of the systems in each batch, in ascending order. This is `None` if systems have been
+    /// added since the batches were last built.
+    batches: Option<Vec<Vec<usize>>>,
 }
 
 impl Schedule {
-    pub fn new(systems: Vec<Box<dyn AnySystem>>, commands: CommandQueue) -> Self {
-        Self { systems, commands }
+    pub fn new(systems: Vec<Box<dyn AnySystem>>) -> Self {
+        Self {
+            systems: systems.into_iter().map(ScheduledSystem::new).collect(),
+            conditions: Vec::new(),
+            commands: CommandQueue::new(),
+            executor: ExecutorKind::default(),
+            batches: None,
+        }
+    }
+
+    pub fn add<M>(&mut self, system: impl IntoSystem<M>) {
+        self.systems
+            .push(ScheduledSystem::new(Box::new(system.into_system())));
+        self.batches = None;
+    }
+
+    pub fn executor(&self) -> ExecutorKind {
+        self.executor
     }
 
-    pub fn add<C: ComponentBundle, R: ResourceBundle>(&mut self, system: System<C, R>) {
-        self.systems.push(Box::new(system));
+    pub fn set_executor(&mut self, executor: ExecutorKind) {
+        self.executor = executor;
     }
 
+    /// Creates the parameter state of any systems and conditions that do not have it yet, and groups the
+    /// systems into batches that can be run in parallel
+    pub fn initialize(&mut self, world: &mut World) {
+        for condition in self.conditions.iter_mut() {
+            condition.initialize(world);
+        }
+
+        for system in self.systems.iter_mut() {
+            system.initialize(world);
+        }
+
+        self.build_batches();
+    }
+
+    fn build_batches(&mut self) {
+        let accesses: Vec<_> = self.systems.iter().map(ScheduledSystem::access).collect();
+
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut system_batches = Vec::with_capacity(self.systems.len());
+
+        for (index, system) in self.systems.iter().enumerate() {
+            let batch = accesses[..index]
+                .iter()
+                .zip(system_batches.iter())
+                .enumerate()
+                .filter(|(other_index, (other_access, _))| {
+                    system.dependencies.contains(other_index)
+                        || !accesses[index].is_compatible(other_access)
+                })
+                .map(|(_, (_, &other_batch))| other_batch + 1)
+                .max()
+                .unwrap_or(0);
+
+            if batch == batches.len() {
+                batches.push(Vec::new());
+            }
+
+            batches[batch].push(index);
+            system_batches.push(batch);
+        }
+
+        self.batches = Some(batches);
+    }
+
+    /// Syncs the systems with any archetypes created since the schedule last ran, then runs them, followed
+    /// by their commands. Syncing first means that components added to new archetypes, from anywhere, are
+    /// seen by change filters on the very next run.
     pub fn update(&mut self, world: &mut World) {
+        self.sync(world);
         self.run_all(world);
         self.flush_commands(world);
-        self.sync(world);
+    }
+
+    /// Returns true if every condition of the schedule is met
+    fn should_run(&mut self, world: &mut World) -> bool {
+        let commands = &mut self.commands;
+        self.conditions
+            .iter_mut()
+            .all(|condition| condition.evaluate(commands, world))
     }
 
     pub fn run_all(&mut self, world: &mut World) {
-        for system in self.systems.iter_mut() {
-            system.run(&mut self.commands, world);
+        if !self.should_run(world) {
+            return;
+        }
+
+        match self.executor {
+            ExecutorKind::SingleThreaded => {
+                for system in self.systems.iter_mut() {
+                    system.run(world);
+                }
+            }
+
+            ExecutorKind::MultiThreaded => {
+                if self.batches.is_none() {
+                    self.initialize(world);
+                }
+
+                // SAFETY: Was just built if it did not exist
+                let batches = unsafe { self.batches.as_ref().unwrap_unchecked() };
+
+                for batch in batches.iter() {
+                    // Systems in the same batch never read what the others write, so they can share a tick
+                    world.increment_tick();
+
+                    let mut jobs: Vec<_> = self
+                        .systems
+                        .iter_mut()
+                        .enumerate()
+                        .filter(|(index, _)| batch.binary_search(index).is_ok())
+                        .map(|(_, system)| system)
+                        .collect();
+
+                    // SAFETY:
+                    // - All systems were initialised when the batches were built
+                    // - Systems in the same batch have compatible access
+                    unsafe { run_parallel(&mut jobs, world) };
+                }
+            }
         }
     }
 
     pub fn flush_commands(&mut self, world: &mut World) {
+        world.increment_tick();
         self.commands.flush(world);
+        for system in self.systems.iter_mut() {
+            system.commands.flush(world);
+        }
     }
 
     pub fn sync(&mut self, world: &mut World) {
+        for condition in self.conditions.iter_mut() {
+            condition.sync(world);
+        }
+
         for system in self.systems.iter_mut() {
             system.sync(world);
         }
     }
+
+    /// Clamps every tick that is becoming too old to be compared with the current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        for condition in self.conditions.iter_mut() {
+            condition.check_ticks(this_run);
+        }
+
+        for system in self.systems.iter_mut() {
+            system.check_ticks(this_run);
+        }
+    }
+}
+
+/// Runs the systems, spread across the worker threads of the world's [TaskPool](super::pool::TaskPool),
+/// as well as the calling thread.
+///
+/// # Safety
+/// - All systems must have been initialised
+/// - The access of every system must be compatible with that of the others
+unsafe fn run_parallel(jobs: &mut [&mut ScheduledSystem], world: &World) {
+    // SAFETY: Deferred to the caller
+    let run_chunk = |chunk: &mut [&mut ScheduledSystem]| {
+        for system in chunk.iter_mut() {
+            unsafe { system.run_unsafe(world) };
+        }
+    };
+
+    if jobs.len() <= 1 {
+        run_chunk(jobs);
+        return;
+    }
+
+    let pool = world.task_pool();
+    let chunk_size = jobs.len().div_ceil(pool.thread_count() + 1);
+
+    pool.scope(|scope| {
+        let mut chunks = jobs.chunks_mut(chunk_size);
+        let local_chunk = chunks.next();
+
+        for chunk in chunks {
+            scope.spawn(move || run_chunk(chunk));
+        }
+
+        if let Some(chunk) = local_chunk {
+            run_chunk(chunk);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::system::{
+        condition::{any_with_component, resource_equals},
+        config::IntoSystemConfig,
+        param::{Local, View},
+    };
+
+    use super::*;
+
+    struct Position(usize);
+    struct Velocity(usize);
+    struct Health;
+    struct Log;
+
+    #[test]
+    fn world_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<World>();
+    }
+
+    #[test]
+    fn batches_follow_access() {
+        let mut world = World::new();
+        world.register_component::<Position>();
+        world.register_component::<Velocity>();
+        world.register_component::<Health>();
+        world.add_resource(Log);
+
+        fn read_position(_: View<&Position>) {}
+        fn write_position(_: View<(&mut Position, &Velocity)>) {}
+        fn read_velocity(_: View<&Velocity>) {}
+        fn write_health(_: View<&mut Health>) {}
+        fn read_log(_: &Log) {}
+        fn write_log(_: &mut Log) {}
+
+        let mut schedule = ScheduleBuilder::new()
+            .add(read_position)
+            .add(write_position)
+            .add(read_velocity)
+            .add(write_health)
+            .add(read_log)
+            .add(write_log)
+            .add(read_position)
+            .build()
+            .unwrap();
+        schedule.initialize(&mut world);
+
+        assert_eq!(
+            schedule.batches,
+            Some(vec![vec![0, 2, 3, 4], vec![1, 5], vec![6]])
+        );
+    }
+
+    #[test]
+    fn conflicting_systems_keep_order() {
+        let mut world = World::new();
+        world.register_component::<Position>();
+        world.register_component::<Velocity>();
+
+        for i in 0..64 {
+            let entity = world.create_entity();
+            world.add_component(entity, Position(i));
+            world.add_component(entity, Velocity(1));
+        }
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let log = order.clone();
+        let movement = move |view: View<(&mut Position, &Velocity)>| {
+            for (mut position, velocity) in view {
+                position.0 += velocity.0;
+            }
+            log.lock().unwrap().push("movement");
+        };
+
+        let log = order.clone();
+        let check = move |view: View<&Position>| {
+            for (i, position) in view.iter().enumerate() {
+                assert_eq!(position.0, i + 1);
+            }
+            log.lock().unwrap().push("check");
+        };
+
+        let log = order.clone();
+        let unrelated = move |_: View<&Velocity>| {
+            log.lock().unwrap().push("unrelated");
+        };
+
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .executor(ExecutorKind::MultiThreaded)
+                .add(movement)
+                .add(unrelated)
+                .add(check)
+                .build()
+                .unwrap(),
+        );
+        world.update();
+
+        let order = order.lock().unwrap();
+        let position = |name| order.iter().position(|&n| n == name).unwrap();
+        assert_eq!(order.len(), 3);
+        assert!(position("movement") < position("check"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn conflicting_parameters() {
+        let mut world = World::new();
+        world.register_component::<Position>();
+
+        fn aliased(_: View<&Position>, _: View<&mut Position>) {}
+
+        world.add_schedule(ScheduleBuilder::new().add(aliased).build().unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_hz() {
+        ScheduleRate::from_hz(0.0);
+    }
+
+    #[test]
+    fn order_constraints() {
+        let mut world = World::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let log = order.clone();
+        let render = move || log.lock().unwrap().push("render");
+        let log = order.clone();
+        let physics = move || log.lock().unwrap().push("physics");
+        let log = order.clone();
+        let input = move || log.lock().unwrap().push("input");
+        let log = order.clone();
+        let audio = move || log.lock().unwrap().push("audio");
+
+        let mut schedule = ScheduleBuilder::new()
+            .add(render.label("render").after("physics"))
+            .add(audio)
+            .add(physics.label("physics"))
+            .add(input.before("physics"))
+            .build()
+            .unwrap();
+        schedule.initialize(&mut world);
+        schedule.run_all(&mut world);
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["audio", "input", "physics", "render"]
+        );
+
+        // None of these access anything, so only the constraints keep them apart
+        schedule.build_batches();
+        assert_eq!(schedule.batches, Some(vec![vec![0, 1], vec![2], vec![3]]));
+    }
+
+    #[test]
+    fn order_cycle() {
+        fn first() {}
+        fn second() {}
+        fn third() {}
+        fn unrelated() {}
+
+        let result = ScheduleBuilder::new()
+            .add(unrelated.label("unrelated").after("missing"))
+            .add(first.label("first").before("second"))
+            .add(second.label("second"))
+            .add(third.label("third").after("second").before("first"))
+            .build();
+
+        let Err(ScheduleError::Cycle(names)) = result else {
+            panic!("expected a cycle");
+        };
+
+        let short_names: Vec<_> = names
+            .iter()
+            .map(|name| name.rsplit("::").next().unwrap())
+            .collect();
+        assert_eq!(short_names, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn run_conditions() {
+        #[derive(PartialEq)]
+        struct Paused(bool);
+
+        let mut world = World::new();
+        world.register_component::<Position>();
+        let paused_id = world.add_resource(Paused(true));
+        let seen = Arc::new(Mutex::new(0));
+
+        let log = seen.clone();
+        let count = move |view: View<&Position>, mut runs: Local<usize>| {
+            *runs += 1;
+            *log.lock().unwrap() = view.iter().count();
+            assert_eq!(*runs, 1);
+        };
+
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .run_if(any_with_component::<Position>())
+                .add(count.run_if(resource_equals(Paused(false))))
+                .build()
+                .unwrap(),
+        );
+
+        // No entity has a position, so the schedule does not run
+        world.update();
+
+        // The system is paused, but is still synced as the new archetype is created
+        let entity = world.create_entity();
+        world.add_component(entity, Position(0));
+        world.update();
+        assert_eq!(*seen.lock().unwrap(), 0);
+
+        unsafe { world.get_mut_resource(paused_id).unwrap().0 = false };
+        world.update();
+        assert_eq!(*seen.lock().unwrap(), 1);
+    }
 }
@@ -1,4 +1,7 @@
+use core::{marker::PhantomData, time::Duration};
+
 use crate::{
+    component::tracking::oldest_tick,
     query::{
         bundle::{ComponentBundle, ResourceBundle},
         iter::ComponentBundleIter,
@@ -7,10 +10,37 @@ use crate::{
     World,
 };
 
-use super::{command::CommandQueue, schedule::Schedule};
+use super::{
+    access::Access,
+    command::CommandQueue,
+    param::{Commands, SystemMeta, SystemParam, SystemParamItem},
+    schedule::{Schedule, ScheduleLabel, ScheduleRate},
+};
+
+/// A [Schedule] that has been added to the world, along with how often it runs
+struct ManagedSchedule {
+    label: Option<ScheduleLabel>,
+    rate: ScheduleRate,
+    schedule: Schedule,
+
+    /// For [ScheduleRate::Startup], whether it has run
+    has_run: bool,
+
+    /// For [ScheduleRate::Fixed], the time that has passed but not yet been stepped through
+    accumulator: Duration,
+}
 
+impl ManagedSchedule {
+    /// Runs the schedule, followed by its own commands
+    fn update(&mut self, world: &mut World) {
+        self.schedule.update(world);
+    }
+}
+
+/// Runs each schedule at its [ScheduleRate]. Schedules are run one after another, each followed by its
+/// own commands, in the order they were added, except that startup schedules that have not yet run go first.
 pub struct SystemManager {
-    schedules: Vec<Schedule>,
+    schedules: Vec<ManagedSchedule>,
 }
 
 impl SystemManager {
@@ -20,101 +50,411 @@ impl SystemManager {
         }
     }
 
-    pub fn add(&mut self, schedule: Schedule) {
-        self.schedules.push(schedule);
+    /// # Panics
+    /// - If there is already a schedule with the label
+    /// - If the rate is [ScheduleRate::Fixed] with a timestep of zero
+    pub fn add(&mut self, label: Option<ScheduleLabel>, rate: ScheduleRate, schedule: Schedule) {
+        #[cold]
+        #[inline(never)]
+        #[track_caller]
+        fn assert_failed(message: &str, label: Option<ScheduleLabel>) -> ! {
+            panic!("{} {:?}", message, label);
+        }
+
+        if label.is_some() && self.schedules.iter().any(|other| other.label == label) {
+            assert_failed("There is already a schedule with the label", label);
+        }
+
+        if rate == ScheduleRate::Fixed(Duration::ZERO) {
+            assert_failed("A fixed timestep must not be zero, for the schedule", label);
+        }
+
+        self.schedules.push(ManagedSchedule {
+            label,
+            rate,
+            schedule,
+            has_run: false,
+            accumulator: Duration::ZERO,
+        });
     }
 
     pub fn update(&mut self, world: &mut World) {
-        self.run_all(world);
-        self.flush_commands(world);
-        self.sync(world);
+        for managed in self.schedules.iter_mut() {
+            if managed.rate == ScheduleRate::Startup && !managed.has_run {
+                managed.update(world);
+                managed.has_run = true;
+            }
+        }
 
-        world.archetype_manager.new_archetypes_queue.clear();
-    }
+        let delta = world.time().delta;
+        let max_steps = world.time().max_fixed_steps();
+
+        for managed in self.schedules.iter_mut() {
+            match managed.rate {
+                ScheduleRate::Startup => {}
+
+                ScheduleRate::EveryUpdate => managed.update(world),
+
+                ScheduleRate::Fixed(step) => {
+                    managed.accumulator += delta;
 
-    pub fn run_all(&mut self, world: &mut World) {
-        for schedule in self.schedules.iter_mut() {
-            schedule.run_all(world);
+                    let mut steps = 0;
+                    while managed.accumulator >= step && steps < max_steps {
+                        managed.accumulator -= step;
+                        steps += 1;
+
+                        world.time_mut().delta = step;
+                        managed.update(world);
+                        world.time_mut().delta = delta;
+                    }
+
+                    // Catching up on the rest would only make the next update longer still
+                    if managed.accumulator >= step {
+                        managed.accumulator = Duration::ZERO;
+                    }
+                }
+            }
         }
     }
 
-    fn flush_commands(&mut self, world: &mut World) {
-        for schedule in self.schedules.iter_mut() {
-            schedule.flush_commands(world);
+    /// Clamps every tick that is becoming too old to be compared with the current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        for managed in self.schedules.iter_mut() {
+            managed.schedule.check_ticks(this_run);
         }
     }
 
-    fn sync(&mut self, world: &mut World) {
-        for schedule in self.schedules.iter_mut() {
-            schedule.sync(world);
+    /// Runs the schedule with the label once, regardless of its rate. Returns false if there is no
+    /// schedule with the label.
+    pub fn run(&mut self, label: ScheduleLabel, world: &mut World) -> bool {
+        match self
+            .schedules
+            .iter_mut()
+            .find(|managed| managed.label == Some(label))
+        {
+            Some(managed) => {
+                managed.update(world);
+                true
+            }
+            None => false,
         }
     }
 }
 
 /// This is a bit ugly, but it basically represents a function that takes an iterator over
 /// a bundle of components, a bundle of resources, and a command queue.
+///
+/// Systems created from a single [Query] are not limited to this function pointer type, as any closure
+/// with the same signature can be used, including those that capture their environment.
 pub type SystemFn<C, R> =
     fn(ComponentBundleIter<'_, '_, C>, <R as ResourceBundle>::Item<'_>, &mut CommandQueue);
 
-/// Every system has its own query that is used to fetch components and resources from the world. These
-/// are then passed to the system function, along with the command queue from the [Schedule] which is a
-/// parent of many systems.
+/// A function that can be run as a system, where each of its parameters is a [SystemParam].
+///
+/// This is implemented for functions taking up to 8 parameters, though a tuple of parameters is also a
+/// parameter, so more can be taken by grouping them. The marker type parameter only exists to allow
+/// for these different implementations.
+///
+/// Systems may be run on other threads, so the function must be [Send] and [Sync].
+pub trait SystemParamFunction<M>: Send + Sync + 'static {
+    type Param: SystemParam;
+    /// The return type of the function, which is `()` for systems and `bool` for conditions
+    type Out;
+
+    fn run(&mut self, params: SystemParamItem<'_, '_, Self::Param>) -> Self::Out;
+}
+
+/// Wraps a function with the signature of a [SystemFn], which takes the iterator and resources of a
+/// single [Query], along with the command queue, so that it can be run as a [SystemParamFunction].
+pub struct QueryFn<C: ComponentBundle, R: ResourceBundle, F> {
+    func: F,
+    _marker: PhantomData<fn() -> (C, R)>,
+}
+
+/// Marker for [SystemParamFunction] on a [QueryFn]
+pub struct QueryFnMarker;
+
+impl<C, R, F> SystemParamFunction<QueryFnMarker> for QueryFn<C, R, F>
+where
+    C: ComponentBundle + 'static,
+    R: ResourceBundle,
+    F: FnMut(ComponentBundleIter<'_, '_, C>, R::Item<'_>, &mut CommandQueue)
+        + Send
+        + Sync
+        + 'static,
+{
+    type Param = (Query<C, R>, Commands<'static>);
+    type Out = ();
+
+    fn run(&mut self, params: SystemParamItem<'_, '_, Self::Param>) {
+        let ((components, resources), mut commands) = params;
+        (self.func)(components, resources, &mut commands)
+    }
+}
+
+macro_rules! impl_system_param_function {
+    ($($P:ident),*) => {
+        impl<Out, Func, $($P: SystemParam),*> SystemParamFunction<fn($($P,)*) -> Out> for Func
+        where
+            Func: Send + Sync + 'static,
+            for<'a> &'a mut Func:
+                FnMut($($P),*) -> Out + FnMut($(SystemParamItem<'_, '_, $P>),*) -> Out,
+        {
+            type Param = ($($P,)*);
+            type Out = Out;
+
+            #[allow(non_snake_case)]
+            fn run(&mut self, params: SystemParamItem<'_, '_, Self::Param>) -> Out {
+                // Calling through a generic function lets the compiler pick the `FnMut` implementation
+                // over the fetched items, rather than the one over the parameter types themselves.
+                #[allow(clippy::too_many_arguments)]
+                fn call_inner<Out, $($P),*>(mut func: impl FnMut($($P),*) -> Out, $($P: $P),*) -> Out {
+                    func($($P),*)
+                }
+
+                let ($($P,)*) = params;
+                call_inner(self, $($P),*)
+            }
+        }
+    };
+}
+
+impl_system_param_function!();
+impl_system_param_function!(P0);
+impl_system_param_function!(P0, P1);
+impl_system_param_function!(P0, P1, P2);
+impl_system_param_function!(P0, P1, P2, P3);
+impl_system_param_function!(P0, P1, P2, P3, P4);
+impl_system_param_function!(P0, P1, P2, P3, P4, P5);
+impl_system_param_function!(P0, P1, P2, P3, P4, P5, P6);
+impl_system_param_function!(P0, P1, P2, P3, P4, P5, P6, P7);
+
+/// A system is a function along with the state of each of its parameters. The state is created when the
+/// system is first added to a world, unless it was provided up front, as with [System::new].
 ///
-/// The query is stored in the system, so that it can be updated when the world is updated.
-pub struct System<C: ComponentBundle, R: ResourceBundle> {
-    query: Query<C, R>,
-    func: SystemFn<C, R>,
-    last_update: u32,
+/// The state is kept in the system, so that it can be updated when the world is updated.
+pub struct System<M, F: SystemParamFunction<M>> {
+    func: F,
+    state: Option<<F::Param as SystemParam>::State>,
+    meta: SystemMeta,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<C, R, F> System<QueryFnMarker, QueryFn<C, R, F>>
+where
+    C: ComponentBundle + 'static,
+    R: ResourceBundle,
+    F: FnMut(ComponentBundleIter<'_, '_, C>, R::Item<'_>, &mut CommandQueue)
+        + Send
+        + Sync
+        + 'static,
+{
+    /// Creates a system from a single query, whose components and resources are passed to the system
+    /// function, along with the command queue from the [Schedule] which is a parent of many systems.
+    pub fn new(query: Query<C, R>, func: F) -> Self {
+        let func = QueryFn {
+            func,
+            _marker: PhantomData,
+        };
+
+        Self::with_state(func, (query, ()))
+    }
 }
 
-impl<C: ComponentBundle, R: ResourceBundle> System<C, R> {
-    pub fn new(query: Query<C, R>, func: SystemFn<C, R>) -> Self {
+impl<M, F: SystemParamFunction<M>> System<M, F> {
+    /// Creates a system from a function, whose parameter state will be created when the system is
+    /// added to a world.
+    pub fn from_fn(func: F) -> Self {
+        Self {
+            func,
+            state: None,
+            meta: SystemMeta::new(core::any::type_name::<F>()),
+            _marker: PhantomData,
+        }
+    }
+
+    fn with_state(func: F, state: <F::Param as SystemParam>::State) -> Self {
+        let mut meta = SystemMeta::new(core::any::type_name::<F>());
+        F::Param::init_access(&state, &mut meta);
+
         Self {
-            query,
             func,
-            last_update: 0,
+            state: Some(state),
+            meta,
+            _marker: PhantomData,
         }
     }
 
-    pub fn into_schedule(self) -> Schedule {
-        Schedule::new(vec![Box::new(self)], CommandQueue::new())
+    pub fn into_schedule(self) -> Schedule
+    where
+        M: 'static,
+        F: SystemParamFunction<M, Out = ()>,
+    {
+        Schedule::new(vec![Box::new(self)])
     }
 
-    pub fn run(&mut self, command_buffer: &mut CommandQueue, world: &mut World) {
-        let iter = self.query.iter(world);
-        let resources = unsafe {
-            R::fetch_item(
-                &world.resource_manager.resources,
-                self.query.res_param_ids,
-            )
-        };
-        (self.func)(iter, resources, command_buffer);
-        self.last_update = world.tick;
+    pub fn name(&self) -> &'static str {
+        self.meta.name
+    }
+
+    /// The components and resources accessed by the system. This is only complete once the system has
+    /// been initialised.
+    pub fn access(&self) -> &Access {
+        self.meta.access()
+    }
+
+    /// Creates the parameter state, if it does not exist yet, and records the access of each parameter
+    ///
+    /// # Panics
+    /// - If the access of any parameters conflict
+    pub fn initialize(&mut self, world: &mut World) {
+        if self.state.is_none() {
+            let state = F::Param::init_state(world, &mut self.meta);
+            F::Param::init_access(&state, &mut self.meta);
+            self.state = Some(state);
+
+            // Everything that can still be compared counts as having changed since a system first runs
+            self.meta.last_run = oldest_tick(world.tick);
+        }
+    }
+
+    pub fn run(&mut self, command_buffer: &mut CommandQueue, world: &mut World) -> F::Out {
+        self.initialize(world);
+        world.increment_tick();
+
+        // SAFETY: The system has been initialised, and has exclusive access to the world
+        unsafe { self.run_unsafe(command_buffer, world) }
+    }
+
+    /// Runs the system with shared access to the world, so that other systems can run at the same time.
+    ///
+    /// # Panics
+    /// - If the system has not been initialised
+    ///
+    /// # Safety
+    /// - No system running at the same time may have access that conflicts with that of this system
+    pub unsafe fn run_unsafe(
+        &mut self,
+        command_buffer: &mut CommandQueue,
+        world: &World,
+    ) -> F::Out {
+        let state = self
+            .state
+            .as_mut()
+            .expect("System must be initialised before being run with shared access to the world");
+
+        // SAFETY:
+        // - The command buffer is borrowed mutably for the duration of the run
+        // - Caller ensures that no other system is accessing the same data mutably
+        let params = unsafe { F::Param::fetch(state, &self.meta, world, command_buffer) };
+        let out = self.func.run(params);
+
+        self.meta.last_run = world.tick;
+
+        out
     }
 
     pub fn sync(&mut self, world: &mut World) {
-        self.query.sync(world)
+        if let Some(state) = self.state.as_mut() {
+            F::Param::sync(state, world);
+        }
+    }
+
+    /// Clamps the tick that the system last ran at, if it is becoming too old to be compared with the
+    /// current one
+    pub fn check_ticks(&mut self, this_run: u32) {
+        self.meta.check_ticks(this_run);
     }
 }
 
-pub trait AnySystem {
+pub trait AnySystem: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn access(&self) -> &Access;
+    fn initialize(&mut self, world: &mut World);
     fn run(&mut self, command_buffer: &mut CommandQueue, world: &mut World);
+    /// # Safety
+    /// - See [System::run_unsafe]
+    unsafe fn run_unsafe(&mut self, command_buffer: &mut CommandQueue, world: &World);
     fn sync(&mut self, world: &mut World);
+    fn check_ticks(&mut self, this_run: u32);
 }
 
-impl<C: ComponentBundle, R: ResourceBundle> AnySystem for System<C, R> {
+impl<M, F: SystemParamFunction<M, Out = ()>> AnySystem for System<M, F> {
+    fn name(&self) -> &'static str {
+        System::name(self)
+    }
+
+    fn access(&self) -> &Access {
+        System::access(self)
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        System::initialize(self, world)
+    }
+
     fn run(&mut self, command_buffer: &mut CommandQueue, world: &mut World) {
         System::run(self, command_buffer, world)
     }
 
+    unsafe fn run_unsafe(&mut self, command_buffer: &mut CommandQueue, world: &World) {
+        // SAFETY: Deferred to the caller
+        unsafe { System::run_unsafe(self, command_buffer, world) }
+    }
+
     fn sync(&mut self, world: &mut World) {
         System::sync(self, world)
     }
+
+    fn check_ticks(&mut self, this_run: u32) {
+        System::check_ticks(self, this_run)
+    }
+}
+
+/// Conversion into a type that can be added to a [Schedule]. This is implemented for [System]s, as well
+/// as any function that is a [SystemParamFunction].
+pub trait IntoSystem<M> {
+    type System: AnySystem + 'static;
+
+    fn into_system(self) -> Self::System;
+}
+
+/// Marker for [IntoSystem] on a [SystemParamFunction]
+pub struct FunctionMarker;
+
+/// Marker for [IntoSystem] on a [System]
+pub struct SystemMarker;
+
+impl<M: 'static, F: SystemParamFunction<M, Out = ()>> IntoSystem<(FunctionMarker, M)> for F {
+    type System = System<M, F>;
+
+    fn into_system(self) -> Self::System {
+        System::from_fn(self)
+    }
+}
+
+impl<M: 'static, F: SystemParamFunction<M, Out = ()>> IntoSystem<(SystemMarker, M)>
+    for System<M, F>
+{
+    type System = Self;
+
+    fn into_system(self) -> Self::System {
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{entity::Entity, query::filter::Tracked, system::schedule::ScheduleBuilder, World};
+    use crate::{
+        component::tracking::CHECK_TICK_INTERVAL,
+        entity::Entity,
+        query::filter::{Added, And, Changed, Not, Tracked},
+        system::{
+            param::{Commands, Local, View},
+            schedule::ScheduleBuilder,
+        },
+        time::Time,
+        World,
+    };
 
     use super::*;
 
@@ -141,7 +481,12 @@ mod tests {
             |components, _, command_buffer| {
                 for (e, s) in components {
                     println!("Speed: {}", s.v);
-                    command_buffer.add_component(e, Health { v: e as usize });
+                    command_buffer.add_component(
+                        e,
+                        Health {
+                            v: e.index() as usize,
+                        },
+                    );
                 }
             },
         );
@@ -151,7 +496,7 @@ mod tests {
             world.add_component(player, Speed { v: i });
         }
 
-        world.add_schedule(ScheduleBuilder::new().add(speed_system).build());
+        world.add_schedule(ScheduleBuilder::new().add(speed_system).build().unwrap());
         // This should run the speed system
         world.update();
 
@@ -165,7 +510,7 @@ mod tests {
             },
         );
 
-        world.add_schedule(ScheduleBuilder::new().add(health_system).build());
+        world.add_schedule(ScheduleBuilder::new().add(health_system).build().unwrap());
         // The speed system adds health components to all entities, so they should move to different archetypes.
         // Calling update should update the archetype ids of all systems, so the speed system should run again,
         // as well as the health system.
@@ -178,23 +523,14 @@ mod tests {
     #[test]
     fn tracking_test() {
         let mut world = World::new();
-        world.register_component::<Speed>();
+        world.register_component_tracked::<Speed>();
         world.register_component::<Health>();
 
-        // BUG: Adding components to an entity, before creating the system that queries for them,
-        //      means that the relevant archetypes will be tracked - great.
+        // NOTE: A system that uses the `flag_modified` command has no effect if the component storage is not
+        //       tracked. This can occur if another system, with identical queries but including a tracked version
+        //       of the component, is not present which means tracking would not be added automatically.
         //
-        //      However, each newly created archetype will be added to a queue that is processed when the
-        //      system manager flushes, as newly created archetypes may be added to existing queries if they match.
-        //
-        //      This means that the archetype will be added twice, as we are not using an actual set, but a vector.
-
-        // BUG: A system that uses the `flag_modified` command, can fail if the component storage is not tracked.
-        //      This can occur if another system, with identical queries but including a tracked version of
-        //      the component, is not present which means tracking would not be added automatically.
-        //
-        //      I think the solution is just add a way to manually add tracking? This might be tricky. An easier way
-        //      would be to require the `Tracked` enum by required for the `flag_modified` command.
+        //       Registering the component as tracked means its storages are always tracked.
 
         let flag_modify_system = System::new(
             world.query::<(Entity, &Speed)>().build(),
@@ -226,7 +562,8 @@ mod tests {
             ScheduleBuilder::new()
                 .add(flag_modify_system)
                 .add(tracked_system)
-                .build(),
+                .build()
+                .unwrap(),
         );
 
         // First update will run nothing, but the flush thereafter will update the relevant archetypes
@@ -278,7 +615,12 @@ mod tests {
             },
         );
 
-        world.add_schedule(ScheduleBuilder::new().add(system_with_resource).build());
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .add(system_with_resource)
+                .build()
+                .unwrap(),
+        );
 
         for i in 0..10 {
             let player = world.create_entity();
@@ -296,4 +638,606 @@ mod tests {
         assert_eq!(global.b, 6);
         assert_eq!(global.c, 8);
     }
+
+    #[test]
+    fn multiple_params() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+
+        fn collide(
+            speeds: View<&Speed, Not<Health>>,
+            healths: View<(Entity, &Health)>,
+            global: &mut Global,
+            mut runs: Local<usize>,
+            mut commands: Commands,
+        ) {
+            *runs += 1;
+            global.a = *runs;
+
+            for s in speeds.iter() {
+                for (e, h) in healths.iter() {
+                    if s.v == h.v {
+                        global.b += 1;
+                        commands.remove_component::<Health>(e);
+                    }
+                }
+            }
+        }
+
+        for i in 0..10 {
+            let entity = world.create_entity();
+            world.add_component(entity, Speed { v: i });
+
+            let entity = world.create_entity();
+            world.add_component(entity, Health { v: i * 2 });
+        }
+
+        world.add_schedule(ScheduleBuilder::new().add(collide).build().unwrap());
+
+        // Parameter state is created when the schedule is added, so the views already match the
+        // existing archetypes
+        world.update();
+        world.update();
+
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 2);
+        // Only the first update finds matches, as those entities then lose their health component
+        assert_eq!(global.b, 5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn multiple_commands() {
+        let mut world = World::new();
+
+        fn aliased(_: Commands, _: Commands) {}
+
+        world.add_schedule(ScheduleBuilder::new().add(aliased).build().unwrap());
+    }
+
+    #[test]
+    fn closure_systems() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+
+        for i in 0..4 {
+            let entity = world.create_entity();
+            world.add_component(entity, Speed { v: i });
+        }
+
+        let scale = 10;
+        let scaled_sum = world
+            .query::<&Speed>()
+            .with_resources::<&mut Global>()
+            .build()
+            .into_system(move |speeds, global, _| {
+                for s in speeds {
+                    global.a += s.v * scale;
+                }
+            });
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let count = move |speeds: View<&Speed>| {
+            sender.send(speeds.iter().count()).unwrap();
+        };
+
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .add(scaled_sum)
+                .add(count)
+                .build()
+                .unwrap(),
+        );
+        world.update();
+
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 60);
+        assert_eq!(receiver.try_recv(), Ok(4));
+    }
+
+    #[test]
+    fn schedule_rates() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        let step = Duration::from_millis(10);
+
+        let startup = |global: &mut Global, mut commands: Commands| {
+            global.a += 1;
+            commands.spawn((Speed { v: 1 },));
+        };
+        let per_update = |global: &mut Global, speeds: View<&Speed>| {
+            global.b = speeds.iter().count();
+        };
+        let fixed = move |global: &mut Global, time: &Time| {
+            assert_eq!(time.delta(), step);
+            global.c += 1;
+        };
+
+        world.add_named_schedule(
+            "update",
+            ScheduleRate::EveryUpdate,
+            ScheduleBuilder::new().add(per_update).build().unwrap(),
+        );
+        world.add_named_schedule(
+            "fixed",
+            ScheduleRate::Fixed(step),
+            ScheduleBuilder::new().add(fixed).build().unwrap(),
+        );
+        world.add_named_schedule(
+            "startup",
+            ScheduleRate::Startup,
+            ScheduleBuilder::new().add(startup).build().unwrap(),
+        );
+
+        world.update_with_delta(Duration::from_millis(25));
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.c), (1, 2));
+        assert_eq!(world.time().delta(), Duration::from_millis(25));
+
+        // The startup schedule flushed its commands before the others ran, and the update schedule has
+        // since synced with the new archetype
+        world.update_with_delta(Duration::from_millis(25));
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b, global.c), (1, 1, 5));
+        assert_eq!(world.time().elapsed(), Duration::from_millis(50));
+
+        world.run_schedule("startup");
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 2);
+
+        // One long update only runs the fixed schedule up to the limit, and the rest of the time is dropped
+        world.time_mut().set_max_fixed_steps(3);
+        world.update_with_delta(Duration::from_secs(10));
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.c, 8);
+
+        world.update_with_delta(Duration::from_millis(15));
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.c, 9);
+    }
+
+    #[test]
+    fn added_and_changed_filters() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        let first = world.spawn((Speed { v: 0 },));
+
+        let count = |global: &mut Global,
+                     added: View<&Speed, Added<Speed>>,
+                     changed: View<&Speed, Changed<Speed>>| {
+            global.a = added.iter().count();
+            global.b = changed.iter().count();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+
+        // Everything counts as added the first time that a system runs
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (1, 1));
+
+        world.spawn((Speed { v: 1 },));
+        world.spawn((Speed { v: 2 },));
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (2, 2));
+
+        // Each change is only seen once
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (0, 0));
+
+        world.insert_bundle(first, (Speed { v: 3 },));
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (0, 1));
+    }
+
+    #[test]
+    fn insert_bundle_keeps_added() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        world.spawn((Speed { v: 0 }, Health { v: 0 }));
+        let entity = world.spawn((Speed { v: 1 },));
+
+        let count = |global: &mut Global,
+                     added: View<&Speed, Added<Speed>>,
+                     changed: View<&Speed, Changed<Speed>>| {
+            global.a = added.iter().count();
+            global.b = changed.iter().count();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+        world.update();
+        world.update();
+
+        // The entity moves to another archetype, but its speed is only overwritten, not added
+        world.insert_bundle(entity, (Speed { v: 2 }, Health { v: 2 }));
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (0, 1));
+    }
+
+    #[test]
+    fn untracked_move_is_not_a_change() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+
+        // Only the archetype with both components is tracked, as that is all the system matches
+        let old = world.spawn((Speed { v: 0 },));
+        world.spawn((Speed { v: 1 }, Health { v: 1 }));
+
+        let count = |global: &mut Global,
+                     added: View<(&Speed, &Health), Added<Speed>>,
+                     changed: View<(&Speed, &Health), Changed<Speed>>| {
+            global.a = added.iter().count();
+            global.b = changed.iter().count();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+        world.update();
+        world.update();
+
+        // Moving into the tracked archetype does not make the old speed count as added or changed
+        world.add_component(old, Health { v: 0 });
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (0, 0));
+    }
+
+    #[test]
+    fn mut_records_changes() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        world.spawn((Speed { v: 0 },));
+
+        let count = |global: &mut Global, changed: View<&Speed, Changed<Speed>>| {
+            global.a = changed.iter().count();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+        world.update();
+
+        for v in 1..4 {
+            world.spawn((Speed { v },));
+        }
+        world.update();
+
+        // Only mutably dereferencing counts as a change
+        let accelerate = |view: View<&mut Speed>| {
+            for mut speed in view {
+                if speed.v % 2 == 0 {
+                    speed.v += 1;
+                }
+            }
+        };
+        world.add_schedule(ScheduleBuilder::new().add(accelerate).build().unwrap());
+        world.update();
+        world.update();
+
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 2);
+    }
+
+    #[test]
+    fn tracked_registration() {
+        let mut world = World::new();
+        world.register_component_tracked::<Speed>();
+        world.register_component::<Health>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+
+        // Spawned before any query tracks the component
+        world.spawn((Speed { v: 0 },));
+
+        let count = |global: &mut Global, added: View<&Speed, Added<Speed>>| {
+            global.a = added.iter().count();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 1);
+
+        // The new archetype is tracked as soon as it is created, rather than when the query syncs with it
+        world.spawn((Speed { v: 1 }, Health { v: 1 }));
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 1);
+    }
+
+    #[test]
+    fn ticks_wrap_around() {
+        let mut world = World::new();
+        world.register_component_tracked::<Speed>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+
+        let count = |global: &mut Global, changed: View<&Speed, Changed<Speed>>| {
+            global.a = changed.iter().count();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+        world.tick = u32::MAX - 1;
+        world.update();
+
+        // Spawned after the tick wrapped around, but the system last ran before it did
+        world.spawn((Speed { v: 0 },));
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 1);
+
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 0);
+
+        // Once the tick has wrapped almost all the way around again, the component would seem to have
+        // changed very recently if its tick had not been clamped in the meantime
+        world.tick = world.tick.wrapping_add(u32::MAX - CHECK_TICK_INTERVAL);
+        world.update();
+        world.tick = world.tick.wrapping_add(CHECK_TICK_INTERVAL - 8);
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 0);
+
+        // Moving a component that changed before the wrap into an archetype where one changed after it must
+        // keep the newer tick as the archetype's last write, or the archetype would be skipped
+        let mut world = World::new();
+        world.register_component_tracked::<Speed>();
+        world.register_component::<Health>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        world.add_schedule(ScheduleBuilder::new().add(count).build().unwrap());
+
+        world.tick = u32::MAX - 3;
+        let old = world.spawn((Speed { v: 0 },));
+        world.update();
+
+        world.tick = 5;
+        world.spawn((Speed { v: 1 }, Health { v: 1 }));
+        world.add_component(old, Health { v: 0 });
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 1);
+    }
+
+    #[test]
+    fn tracked_per_system() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        world.spawn((Speed { v: 0 },));
+
+        let modified =
+            |view: &View<Tracked<&Speed>>| view.iter().filter(|speed| speed.is_modified()).count();
+        let every_update = move |global: &mut Global, view: View<Tracked<&Speed>>| {
+            global.a = modified(&view);
+        };
+        let on_demand = move |global: &mut Global, view: View<Tracked<&Speed>>| {
+            global.b = modified(&view);
+        };
+        world.add_schedule(ScheduleBuilder::new().add(every_update).build().unwrap());
+        world.add_named_schedule(
+            "on_demand",
+            ScheduleRate::Startup,
+            ScheduleBuilder::new().add(on_demand).build().unwrap(),
+        );
+
+        // Everything counts as modified the first time that a system runs
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b), (1, 1));
+
+        world.spawn((Speed { v: 1 },));
+        world.update();
+        world.spawn((Speed { v: 2 },));
+        world.update();
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, 1);
+
+        // A system that runs less often still sees every change since it last ran, exactly once
+        world.run_schedule("on_demand");
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.b, 2);
+
+        world.run_schedule("on_demand");
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.b, 0);
+    }
+
+    #[test]
+    fn wide_bundles() {
+        let mut world = World::new();
+        world.register_component::<u8>();
+        world.register_component::<u16>();
+        world.register_component::<u32>();
+        world.register_component::<u64>();
+        world.register_component::<u128>();
+        world.register_component::<usize>();
+        world.register_component::<i8>();
+        world.register_component::<i16>();
+        world.register_component::<i32>();
+        world.register_component::<i64>();
+        world.register_component::<i128>();
+        world.register_component::<isize>();
+        world.register_component::<f32>();
+        world.register_component::<f64>();
+        world.register_component::<char>();
+        world.register_component::<bool>();
+        world.register_component::<String>();
+
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+        world.add_resource(1u8);
+        world.add_resource(1u16);
+        world.add_resource(1u32);
+        world.add_resource(1u64);
+        world.add_resource(1u128);
+        world.add_resource(1usize);
+        world.add_resource(1i8);
+        world.add_resource(1i16);
+        world.add_resource(1i32);
+        world.add_resource(1i64);
+        world.add_resource(1i128);
+        world.add_resource(1isize);
+        world.add_resource(1f32);
+        world.add_resource(1f64);
+        world.add_resource('1');
+
+        for i in 0..10u8 {
+            let entity = world.spawn((
+                i, 1u16, 2u32, 3u64, 4u128, 5usize, 6i8, 7i16, 8i32, 9i64, 10i128, 11isize, 12f32,
+                13f64, 'x', true,
+            ));
+            if i % 2 == 0 {
+                world.add_component(entity, String::from("filtered"));
+            }
+        }
+
+        type Wide = (
+            &'static u8,
+            &'static u16,
+            &'static u32,
+            &'static u64,
+            &'static u128,
+            &'static usize,
+            &'static i8,
+            &'static i16,
+            &'static i32,
+            &'static i64,
+            &'static i128,
+            &'static isize,
+            &'static f32,
+            &'static f64,
+            &'static char,
+            &'static mut bool,
+        );
+        type WideFilter = (
+            And<u8>,
+            And<u16>,
+            And<u32>,
+            And<u64>,
+            And<u128>,
+            And<usize>,
+            And<i8>,
+            And<i16>,
+            And<i32>,
+            And<i64>,
+            And<i128>,
+            And<isize>,
+            And<f32>,
+            And<f64>,
+            And<char>,
+            Not<String>,
+        );
+        type WideResources = (
+            &'static u8,
+            &'static u16,
+            &'static u32,
+            &'static u64,
+            &'static u128,
+            &'static usize,
+            &'static i8,
+            &'static i16,
+            &'static i32,
+            &'static i64,
+            &'static i128,
+            &'static isize,
+            &'static f32,
+            &'static f64,
+            &'static char,
+            &'static mut Global,
+        );
+
+        let wide = System::new(
+            world
+                .query::<Wide>()
+                .filter::<WideFilter>()
+                .with_resources::<WideResources>()
+                .build(),
+            |iter, resources, _| {
+                let (r0, r1, r2, r3, r4, r5, r6, r7, r8, r9, r10, r11, r12, r13, r14, global) =
+                    resources;
+                let ones = [*r0 as usize, *r1 as usize, *r2 as usize, *r3 as usize]
+                    .into_iter()
+                    .chain([*r4 as usize, *r5, *r6 as usize, *r7 as usize])
+                    .chain([*r8 as usize, *r9 as usize, *r10 as usize, *r11 as usize])
+                    .chain([
+                        *r12 as usize,
+                        *r13 as usize,
+                        r14.to_digit(10).unwrap() as usize,
+                    ]);
+                global.b = ones.sum();
+
+                for (a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, mut p) in iter {
+                    *p = false;
+                    global.a += 1;
+                    global.c = [*b as usize, *c as usize, *d as usize, *e as usize, *f]
+                        .into_iter()
+                        .chain([*g as usize, *h as usize, *i as usize, *j as usize])
+                        .chain([*k as usize, *l as usize, *m as usize, *n as usize])
+                        .sum::<usize>()
+                        + (*o == 'x') as usize
+                        + *a as usize % 2;
+                }
+            },
+        );
+
+        // Nested component, filter and resource tuples behave the same as flat ones
+        let nested = System::new(
+            world
+                .query::<(&u8, (&u16, (Entity,)))>()
+                .filter::<(And<bool>, (Not<String>, (And<char>,)))>()
+                .with_resources::<(&u8, (&u16, (&char, &mut Global)))>()
+                .build(),
+            |iter, (one, (_, (_, global))), _| {
+                global.b += *one as usize * 100;
+                global.a += iter.count();
+            },
+        );
+
+        world.add_schedule(
+            ScheduleBuilder::new()
+                .add(wide)
+                .add(nested)
+                .build()
+                .unwrap(),
+        );
+        world.update();
+
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!((global.a, global.b, global.c), (10, 115, 93));
+
+        let query = world.query::<(&u8, &bool)>().build();
+        assert!(query.iter(&world).all(|(i, flag)| *flag == (i % 2 == 0)));
+    }
+
+    #[test]
+    fn view_iter_mut() {
+        let mut world = World::new();
+        world.register_component::<Speed>();
+        world.register_component::<Health>();
+        let global_id = world.add_resource(Global { a: 0, b: 0, c: 0 });
+
+        for i in 0..10 {
+            world.spawn((Speed { v: i }, Health { v: 1 }));
+        }
+
+        let entity = world.spawn((Speed { v: 100 }, Health { v: 1 }));
+
+        let accelerate = move |mut view: View<(&mut Speed, &Health)>, global: &mut Global| {
+            for (mut speed, health) in view.iter_mut() {
+                speed.v += health.v;
+            }
+
+            let (mut speed, _) = view.get_mut(entity).unwrap();
+            speed.v *= 2;
+            global.a = view.iter_mut().map(|(speed, _)| speed.v).sum();
+        };
+        world.add_schedule(ScheduleBuilder::new().add(accelerate).build().unwrap());
+        world.update();
+
+        let global = world.get_resource(global_id).unwrap();
+        assert_eq!(global.a, (1..=10).sum::<usize>() + 202);
+    }
 }
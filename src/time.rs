@@ -0,0 +1,65 @@
+use core::time::Duration;
+
+/// The time that has passed between updates of the [World](crate::World). This resource is always present.
+///
+/// While a schedule with a [ScheduleRate::Fixed](crate::ScheduleRate::Fixed) rate is running, the delta is
+/// its fixed timestep instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    pub(crate) delta: Duration,
+    pub(crate) elapsed: Duration,
+    max_fixed_steps: u32,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            max_fixed_steps: Self::DEFAULT_MAX_FIXED_STEPS,
+        }
+    }
+}
+
+impl Time {
+    /// The default for [Time::max_fixed_steps]
+    pub const DEFAULT_MAX_FIXED_STEPS: u32 = 8;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most times that a schedule with a [ScheduleRate::Fixed](crate::ScheduleRate::Fixed) rate runs in
+    /// a single update. Any time left over beyond this is dropped, so that one long update does not make
+    /// every update after it fall further behind.
+    pub fn max_fixed_steps(&self) -> u32 {
+        self.max_fixed_steps
+    }
+
+    pub fn set_max_fixed_steps(&mut self, steps: u32) {
+        self.max_fixed_steps = steps;
+    }
+
+    /// The time that passed since the previous update
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// The total time that has passed over every update
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub(crate) fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+    }
+}
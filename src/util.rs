@@ -34,3 +34,18 @@ pub unsafe fn get_two_mut_unchecked<'a, T>(
 
     unsafe { (&mut *ptr1, &mut *ptr2) }
 }
+
+/// Invokes the macro once for every prefix of the list of type parameters and tuple indices, so that
+/// it is implemented for tuples of each length up to that of the list
+macro_rules! all_tuples {
+    ($m:ident, $($P:ident $i:tt),+) => {
+        $crate::util::all_tuples!(@prefix $m; []; $($P $i),+);
+    };
+    (@prefix $m:ident; [$($done:ident $d:tt),*]; $P:ident $i:tt $(, $rest:ident $r:tt)*) => {
+        $m!($($done $d,)* $P $i);
+        $crate::util::all_tuples!(@prefix $m; [$($done $d,)* $P $i]; $($rest $r),*);
+    };
+    (@prefix $m:ident; [$($done:ident $d:tt),*];) => {};
+}
+
+pub(crate) use all_tuples;
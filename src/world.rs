@@ -1,11 +1,22 @@
+use core::{num::NonZeroUsize, time::Duration};
+use std::{sync::OnceLock, thread, time::Instant};
+
 use crate::{
     archetype::ArchetypeManager,
-    component::{Component, ComponentManager},
+    component::{
+        bundle::Bundle, removed::RemovedComponentEvents, tracking::CHECK_TICK_INTERVAL, Component,
+        ComponentHook, ComponentHooks, ComponentID, ComponentManager,
+    },
     entity::{Entity, EntityManager},
     event::{EventManager, Events},
     query::{bundle::ComponentBundle, QueryBuilder},
     resource::{Resource, ResourceId, ResourceManager},
-    system::{schedule::Schedule, SystemManager},
+    system::{
+        pool::TaskPool,
+        schedule::{Schedule, ScheduleLabel, ScheduleRate},
+        SystemManager,
+    },
+    time::Time,
 };
 
 pub struct World {
@@ -15,22 +26,48 @@ pub struct World {
     pub(crate) system_manager: SystemManager,
     pub(crate) resource_manager: ResourceManager,
     pub(crate) event_manager: EventManager,
+    pub(crate) removed_components: RemovedComponentEvents,
+    /// Stamped onto components as they are added or changed. It starts at 1, so that anything stamped
+    /// with it counts as changed for a system that has never run.
     pub(crate) tick: u32,
+    /// The tick at which old ticks were last clamped
+    last_check_tick: u32,
+    time_id: ResourceId<Time>,
+    last_update: Option<Instant>,
+    /// Worker threads for running systems in parallel. These are only spawned once they are first needed.
+    task_pool: OnceLock<TaskPool>,
 }
 
 impl World {
     pub fn new() -> Self {
+        let mut resource_manager = ResourceManager::new();
+        let time_id = resource_manager.add(Time::new());
+
         Self {
             entity_manager: EntityManager::new(),
             archetype_manager: ArchetypeManager::new(),
             component_manager: ComponentManager::new(),
             system_manager: SystemManager::new(),
-            resource_manager: ResourceManager::new(),
+            resource_manager,
             event_manager: EventManager::new(),
-            tick: 0,
+            removed_components: RemovedComponentEvents::new(),
+            tick: 1,
+            last_check_tick: 1,
+            time_id,
+            last_update: None,
+            task_pool: OnceLock::new(),
         }
     }
 
+    /// The pool of worker threads shared by every schedule. There is one worker for each thread available
+    /// besides the calling one, as it runs jobs too.
+    pub(crate) fn task_pool(&self) -> &TaskPool {
+        self.task_pool.get_or_init(|| {
+            let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+            TaskPool::new(threads - 1)
+        })
+    }
+
     #[inline]
     pub fn create_entity(&mut self) -> Entity {
         let entity = self.entity_manager.create();
@@ -45,12 +82,87 @@ impl World {
         entity
     }
 
+    /// Creates an entity with every component in the bundle, placing it directly into the archetype
+    /// for the bundle rather than moving it once per component.
+    ///
+    /// # Panics
+    /// - If any component type in the bundle has not been registered
+    /// - If the same component type appears more than once in the bundle
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.archetype_manager.spawn_bundle(
+            bundle,
+            &self.component_manager,
+            &mut self.entity_manager,
+            self.tick,
+        );
+
+        if self.component_manager.has_hooks() {
+            let bundle_id = B::parameter_ids(&self.component_manager);
+            self.run_hooks(entity, bundle_id.as_ref(), |hooks| hooks.on_add);
+            self.run_hooks(entity, bundle_id.as_ref(), |hooks| hooks.on_insert);
+        }
+
+        entity
+    }
+
+    /// Creates an entity for each bundle, returning them in the same order. The archetype is resolved
+    /// once for the whole batch, and storage space is reserved up front.
+    ///
+    /// # Panics
+    /// - If any component type in the bundle has not been registered
+    /// - If the same component type appears more than once in the bundle
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> Vec<Entity> {
+        let entities = self.archetype_manager.spawn_batch(
+            bundles,
+            &self.component_manager,
+            &mut self.entity_manager,
+            self.tick,
+        );
+
+        if self.component_manager.has_hooks() {
+            let bundle_id = B::parameter_ids(&self.component_manager);
+            for &entity in entities.iter() {
+                self.run_hooks(entity, bundle_id.as_ref(), |hooks| hooks.on_add);
+                self.run_hooks(entity, bundle_id.as_ref(), |hooks| hooks.on_insert);
+            }
+        }
+
+        entities
+    }
+
     #[inline]
     pub fn delete_entity(&mut self, entity: Entity) {
         if !self.entity_manager.alive(entity) {
             return;
         }
 
+        if self.component_manager.has_hooks() {
+            // SAFETY: We just checked that the entity is alive, and the archetype ID of an alive entity
+            //         is valid
+            let comp_ids = unsafe {
+                let entity_record = self.entity_manager.get_record(entity);
+                self.archetype_manager
+                    .get(entity_record.archetype_id)
+                    .comp_ids()
+                    .to_vec()
+            };
+            self.run_hooks(entity, &comp_ids, |hooks| hooks.on_remove);
+
+            // A hook may have deleted the entity itself
+            if !self.entity_manager.alive(entity) {
+                return;
+            }
+        }
+
+        // SAFETY: We just checked that the entity is alive, and the archetype ID of an alive entity is valid
+        let arche = unsafe {
+            let entity_record = self.entity_manager.get_record(entity);
+            self.archetype_manager.get(entity_record.archetype_id)
+        };
+        for &comp_id in arche.comp_ids() {
+            self.removed_components.record(comp_id, entity);
+        }
+
         // SAFETY: We just checked that the entity is alive
         unsafe {
             self.archetype_manager
@@ -67,10 +179,27 @@ impl World {
 
     /// Registers the provided component in the current view, creating a corresponding component manager
     pub fn register_component<C: Component>(&mut self) {
-        self.component_manager.register::<C>()
+        self.component_manager.register::<C>();
+    }
+
+    /// Registers the provided component, along with hooks that are run when it is added to, or removed
+    /// from, an entity. If the component is already registered, its hooks are replaced.
+    pub fn register_component_with_hooks<C: Component>(&mut self, hooks: ComponentHooks) {
+        self.component_manager.register_with_hooks::<C>(hooks);
+    }
+
+    /// Registers the provided component, such that changes to it are always tracked. Otherwise, a component
+    /// is only tracked in archetypes matched by a query that tracks it, from when the query is created.
+    ///
+    /// This is worthwhile for components that are filtered by [Added](crate::Added) or
+    /// [Changed](crate::Changed), so that no change is missed, or that are flagged as modified with
+    /// commands. If the component is already registered, tracking is enabled in its existing archetypes.
+    pub fn register_component_tracked<C: Component>(&mut self) {
+        let comp_id = self.component_manager.register_tracked::<C>();
+        self.archetype_manager.enable_tracking(comp_id);
     }
 
-    pub fn register_event<E: 'static>(&mut self) {
+    pub fn register_event<E: Send + Sync + 'static>(&mut self) {
         let events = Events::<E>::new();
         let id = self.add_resource(events);
         self.event_manager.register_event(id);
@@ -109,8 +238,15 @@ impl World {
                 entity,
                 &self.component_manager,
                 &mut self.entity_manager,
+                self.tick,
             )
         };
+
+        if self.component_manager.has_hooks() {
+            let comp_id = self.component_manager.get_id::<C>();
+            self.run_hooks(entity, &[comp_id], |hooks| hooks.on_add);
+            self.run_hooks(entity, &[comp_id], |hooks| hooks.on_insert);
+        }
     }
 
     /// Removes the component of the specified type, for specified entity, in the current view
@@ -122,12 +258,111 @@ impl World {
             return;
         }
 
+        let comp_id = self.component_manager.get_id::<C>();
+
+        if self.component_manager.has_hooks() {
+            self.run_hooks(entity, &[comp_id], |hooks| hooks.on_remove);
+
+            // A hook may have removed the component, or deleted the entity, itself
+            if !self.has_component::<C>(entity) {
+                return;
+            }
+        }
+
+        self.removed_components.record(comp_id, entity);
+
         // SAFETY: `has_component` already checked that the entity is alive
         unsafe {
             self.archetype_manager.remove_component::<C>(
                 entity,
                 &self.component_manager,
                 &mut self.entity_manager,
+                self.tick,
+            )
+        };
+    }
+
+    /// Sets every component in the bundle for the specified entity, overwriting any that it already has.
+    /// The entity is moved to its new archetype at most once.
+    ///
+    /// # Panics
+    /// - If any component type in the bundle has not been registered
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        if !self.entity_manager.alive(entity) {
+            return;
+        }
+
+        // Components that the entity does not have yet, which are the only ones to run `on_add` hooks for
+        let added: Vec<ComponentID> = if self.component_manager.has_hooks() {
+            let bundle_id = B::parameter_ids(&self.component_manager);
+            bundle_id
+                .as_ref()
+                .iter()
+                .copied()
+                .filter(|&comp_id| !self.entity_has_component(entity, comp_id))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // SAFETY: We just checked that the entity is alive
+        unsafe {
+            self.archetype_manager.insert_bundle(
+                bundle,
+                entity,
+                &self.component_manager,
+                &mut self.entity_manager,
+                self.tick,
+            )
+        };
+
+        if self.component_manager.has_hooks() {
+            let bundle_id = B::parameter_ids(&self.component_manager);
+            self.run_hooks(entity, &added, |hooks| hooks.on_add);
+            self.run_hooks(entity, bundle_id.as_ref(), |hooks| hooks.on_insert);
+        }
+    }
+
+    /// Removes every component in the bundle that the specified entity has. The entity is moved to its
+    /// new archetype at most once.
+    ///
+    /// # Panics
+    /// - If any component type in the bundle has not been registered
+    pub fn remove_bundle<B: Bundle>(&mut self, entity: Entity) {
+        if !self.entity_manager.alive(entity) {
+            return;
+        }
+
+        let bundle_id = B::parameter_ids(&self.component_manager);
+
+        if self.component_manager.has_hooks() {
+            let removed: Vec<ComponentID> = bundle_id
+                .as_ref()
+                .iter()
+                .copied()
+                .filter(|&comp_id| self.entity_has_component(entity, comp_id))
+                .collect();
+            self.run_hooks(entity, &removed, |hooks| hooks.on_remove);
+
+            // A hook may have deleted the entity itself
+            if !self.entity_manager.alive(entity) {
+                return;
+            }
+        }
+
+        for &comp_id in bundle_id.as_ref().iter() {
+            if self.entity_has_component(entity, comp_id) {
+                self.removed_components.record(comp_id, entity);
+            }
+        }
+
+        // SAFETY: We just checked that the entity is alive
+        unsafe {
+            self.archetype_manager.remove_bundle::<B>(
+                entity,
+                &self.component_manager,
+                &mut self.entity_manager,
+                self.tick,
             )
         };
     }
@@ -188,6 +423,40 @@ impl World {
         Some(component)
     }
 
+    /// Returns true if the entity is alive and has the component with the ID
+    fn entity_has_component(&self, entity: Entity, comp_id: ComponentID) -> bool {
+        if !self.entity_manager.alive(entity) {
+            return false;
+        }
+
+        // SAFETY: We just checked that the entity is alive, and the archetype ID of an alive entity is valid
+        unsafe {
+            let entity_record = self.entity_manager.get_record(entity);
+            self.archetype_manager
+                .get(entity_record.archetype_id)
+                .has_component(comp_id)
+        }
+    }
+
+    /// Runs the selected hook of each component for the entity, in order. Hooks stop being run if one of
+    /// them deletes the entity.
+    fn run_hooks(
+        &mut self,
+        entity: Entity,
+        comp_ids: &[ComponentID],
+        select: fn(&ComponentHooks) -> Option<ComponentHook>,
+    ) {
+        for &comp_id in comp_ids.iter() {
+            if !self.entity_manager.alive(entity) {
+                return;
+            }
+
+            if let Some(hook) = select(&self.component_manager.get_metadata(comp_id).hooks) {
+                hook(self, entity);
+            }
+        }
+    }
+
     pub fn add_resource<R: Resource>(&mut self, resource: R) -> ResourceId<R> {
         self.resource_manager.add(resource)
     }
@@ -244,8 +513,73 @@ impl World {
         unsafe { self.resource_manager.get_mut_unchecked::<R>(id) }
     }
 
-    pub fn add_schedule(&mut self, schedule: Schedule) {
-        self.system_manager.add(schedule);
+    /// Adds a schedule that runs on every update
+    pub fn add_schedule(&mut self, mut schedule: Schedule) {
+        schedule.initialize(self);
+        self.system_manager
+            .add(None, ScheduleRate::EveryUpdate, schedule);
+    }
+
+    /// Adds a schedule that runs at the given rate, and can also be run on demand with
+    /// [run_schedule](Self::run_schedule)
+    ///
+    /// # Panics
+    /// - If there is already a schedule with the label
+    /// - If the rate is [ScheduleRate::Fixed] with a timestep of zero
+    pub fn add_named_schedule(
+        &mut self,
+        label: ScheduleLabel,
+        rate: ScheduleRate,
+        mut schedule: Schedule,
+    ) {
+        schedule.initialize(self);
+        self.system_manager.add(Some(label), rate, schedule);
+    }
+
+    /// Runs the schedule with the label once, followed by its commands, regardless of its rate
+    ///
+    /// # Panics
+    /// - If there is no schedule with the label
+    pub fn run_schedule(&mut self, label: ScheduleLabel) {
+        #[cold]
+        #[inline(never)]
+        #[track_caller]
+        fn assert_failed(label: ScheduleLabel) -> ! {
+            panic!("There is no schedule with the label {:?}", label);
+        }
+
+        let mut system_manager = core::mem::replace(&mut self.system_manager, SystemManager::new());
+        let found = system_manager.run(label, self);
+        self.system_manager = system_manager;
+
+        if !found {
+            assert_failed(label);
+        }
+    }
+
+    pub fn time(&self) -> &Time {
+        // SAFETY: The time resource is added when the world is created, and never removed
+        unsafe { self.resource_manager.get_unchecked(self.time_id) }
+    }
+
+    pub(crate) fn time_mut(&mut self) -> &mut Time {
+        // SAFETY: The time resource is added when the world is created, and never removed. The world is
+        //         borrowed mutably, so the resource cannot be borrowed anywhere else.
+        unsafe { self.resource_manager.get_mut_unchecked(self.time_id) }
+    }
+
+    /// Advances the tick, so that changes made from now on are seen by systems that have already run
+    pub(crate) fn increment_tick(&mut self) -> u32 {
+        self.tick = self.tick.wrapping_add(1);
+        self.tick
+    }
+
+    /// Clamps every tick that is becoming too old to be compared with the current one, before the world
+    /// tick wraps around far enough to make it ambiguous
+    pub(crate) fn check_ticks(&mut self) {
+        self.archetype_manager.check_ticks(self.tick);
+        self.system_manager.check_ticks(self.tick);
+        self.last_check_tick = self.tick;
     }
 
     pub fn query<C: ComponentBundle>(&mut self) -> QueryBuilder<'_, (C,)> {
@@ -256,12 +590,33 @@ impl World {
         )
     }
 
+    /// Runs each schedule at its rate, using the real time that has passed since the previous update. The
+    /// first update is counted as taking no time.
     pub fn update(&mut self) {
+        let now = Instant::now();
+        let delta = self
+            .last_update
+            .map_or(Duration::ZERO, |last_update| now - last_update);
+        self.last_update = Some(now);
+
+        self.update_with_delta(delta);
+    }
+
+    /// Runs each schedule at its rate, as if the given time has passed since the previous update. This is
+    /// useful for deterministic simulation, where time is not measured.
+    pub fn update_with_delta(&mut self, delta: Duration) {
+        self.time_mut().advance(delta);
+
         // TODO: Make this more efficient rather than cloning the system manager
         let mut system_manager = core::mem::replace(&mut self.system_manager, SystemManager::new());
         system_manager.update(self);
         self.system_manager = system_manager;
-        // self.event_manager.clear_events(&self.resource_manager);
-        self.tick += 1;
+        self.event_manager.clear_events(&mut self.resource_manager);
+        self.removed_components.clear();
+        self.increment_tick();
+
+        if self.tick.wrapping_sub(self.last_check_tick) >= CHECK_TICK_INTERVAL {
+            self.check_ticks();
+        }
     }
 }